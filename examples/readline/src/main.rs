@@ -1,5 +1,6 @@
 use async_std::stream;
-use rustyline_async::{Readline, ReadlineError};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use rustyline_async::{Binding, Completion, Readline, ReadlineError};
 
 use std::{io::Write, time::Duration};
 
@@ -12,6 +13,27 @@ async fn main() -> Result<(), ReadlineError> {
 
 	let (mut rl, mut stdout) = Readline::new("> ".to_owned()).unwrap();
 	// rl.set_max_history(10);
+	rl.set_completer(|line: &str, pos: usize| {
+		let commands = [
+			"start task",
+			"stop task",
+			"start logging",
+			"stop logging",
+			"info",
+		];
+		commands
+			.iter()
+			.filter(|command| command.starts_with(line))
+			.map(|command| Completion::new(0..pos, *command))
+			.collect()
+	});
+
+	// Ctrl-X Ctrl-U turns the line into uppercase
+	let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+	rl.bind(
+		&[ctrl('x'), ctrl('u')],
+		Binding::callback(|buffer| buffer.line = buffer.line.to_uppercase()),
+	);
 
 	simplelog::WriteLogger::init(
 		log::LevelFilter::Debug,
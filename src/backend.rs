@@ -0,0 +1,356 @@
+use std::{
+	collections::VecDeque,
+	io::{self, stdout, Stdout, Write},
+	pin::Pin,
+	sync::{Arc, Mutex, MutexGuard, PoisonError},
+	task::{Context, Poll, Waker},
+};
+
+use crossterm::{
+	event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+	terminal,
+};
+use futures::{prelude::*, stream::BoxStream};
+
+use crate::decode::Decoder;
+
+/// Input read from a [`Backend`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputEvent {
+	Event(Event),
+	/// Text pasted at once, inserted as a single edit
+	Paste(String),
+}
+
+/// Terminal that `Readline` reads input from and renders the line to.
+/// Output is written through the `Write` implementation.
+pub trait Backend: Write + Send {
+	/// Poll for the next input event, returning `None` once input has ended
+	fn poll_event(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<InputEvent>>>;
+	/// Poll for writing out the buffered output, for backends whose output is written asynchronously
+	fn poll_flush(&mut self, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		Poll::Ready(self.flush())
+	}
+	/// Size of the terminal in columns and rows
+	fn size(&self) -> io::Result<(u16, u16)>;
+	fn enable_raw_mode(&mut self) -> io::Result<()>;
+	fn disable_raw_mode(&mut self) -> io::Result<()>;
+}
+
+/// Backend for the terminal of the process, using crossterm
+pub struct CrosstermBackend {
+	stdout: Stdout,
+	events: BoxStream<'static, io::Result<Event>>,
+}
+impl CrosstermBackend {
+	pub fn new() -> Self {
+		Self::with_events(EventStream::new())
+	}
+	/// Backend reading the given events instead of those of the terminal
+	fn with_events(events: impl Stream<Item = io::Result<Event>> + Send + 'static) -> Self {
+		Self {
+			stdout: stdout(),
+			events: events.boxed(),
+		}
+	}
+}
+impl Default for CrosstermBackend {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+impl Write for CrosstermBackend {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.stdout.write(buf)
+	}
+	fn flush(&mut self) -> io::Result<()> {
+		self.stdout.flush()
+	}
+}
+impl Backend for CrosstermBackend {
+	fn poll_event(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<InputEvent>>> {
+		loop {
+			let event = match futures::ready!(self.events.poll_next_unpin(cx)) {
+				Some(Ok(event)) => event,
+				Some(Err(error)) => return Poll::Ready(Some(Err(error))),
+				None => return Poll::Ready(None),
+			};
+			return Poll::Ready(Some(Ok(match event {
+				Event::Paste(text) => InputEvent::Paste(text),
+				// Only presses edit the line, terminals reporting releases report them as well
+				Event::Key(KeyEvent {
+					kind: KeyEventKind::Release,
+					..
+				}) => continue,
+				event => InputEvent::Event(event),
+			})));
+		}
+	}
+	fn size(&self) -> io::Result<(u16, u16)> {
+		terminal::size()
+	}
+	fn enable_raw_mode(&mut self) -> io::Result<()> {
+		terminal::enable_raw_mode()
+	}
+	fn disable_raw_mode(&mut self) -> io::Result<()> {
+		terminal::disable_raw_mode()
+	}
+}
+
+/// Backend for a remote terminal, e.g. one connected over a socket or SSH channel.
+/// Input bytes are decoded into key events, and output is buffered and written out
+/// whenever `Readline` flushes it or polls for input.
+/// Raw mode is left to the other end of the connection.
+pub struct StreamBackend<R, W> {
+	reader: R,
+	writer: W,
+	output: Vec<u8>,
+	decoder: Decoder,
+	size: (u16, u16),
+	resizes: stream::Fuse<BoxStream<'static, (u16, u16)>>,
+	ended: bool,
+}
+impl<R, W> StreamBackend<R, W>
+where
+	R: AsyncRead + Unpin + Send,
+	W: AsyncWrite + Unpin + Send,
+{
+	/// Create a backend reading raw input from `reader` and writing output to `writer`,
+	/// for a terminal with the given number of columns and rows.
+	/// Sizes received from `resizes` are reported as resize events.
+	pub fn new(
+		reader: R,
+		writer: W,
+		size: (u16, u16),
+		resizes: impl Stream<Item = (u16, u16)> + Send + 'static,
+	) -> Self {
+		Self {
+			reader,
+			writer,
+			output: Vec::new(),
+			decoder: Decoder::default(),
+			size,
+			resizes: resizes.boxed().fuse(),
+			ended: false,
+		}
+	}
+	fn poll_write_output(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		while !self.output.is_empty() {
+			let written = futures::ready!(Pin::new(&mut self.writer).poll_write(cx, &self.output))?;
+			if written == 0 {
+				return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+			}
+			self.output.drain(..written);
+		}
+		Pin::new(&mut self.writer).poll_flush(cx)
+	}
+}
+impl<R, W> Write for StreamBackend<R, W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.output.extend_from_slice(buf);
+		Ok(buf.len())
+	}
+	/// Output is only written out asynchronously, see [`Backend::poll_flush`]
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+impl<R, W> Backend for StreamBackend<R, W>
+where
+	R: AsyncRead + Unpin + Send,
+	W: AsyncWrite + Unpin + Send,
+{
+	fn poll_event(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<InputEvent>>> {
+		// Write out the output of synchronous calls like `Readline::set_prompt`
+		if let Poll::Ready(Err(error)) = self.poll_write_output(cx) {
+			return Poll::Ready(Some(Err(error)));
+		}
+		if let Poll::Ready(Some((columns, rows))) = self.resizes.poll_next_unpin(cx) {
+			self.size = (columns, rows);
+			return Poll::Ready(Some(Ok(InputEvent::Event(Event::Resize(columns, rows)))));
+		}
+		let mut buf = [0; 1024];
+		loop {
+			if let Some(input) = self.decoder.next(self.ended) {
+				return Poll::Ready(Some(Ok(input)));
+			}
+			if self.ended {
+				return Poll::Ready(self.decoder.finish().map(Ok));
+			}
+			match Pin::new(&mut self.reader).poll_read(cx, &mut buf) {
+				Poll::Ready(Ok(0)) => self.ended = true,
+				Poll::Ready(Ok(len)) => self.decoder.push(&buf[..len]),
+				Poll::Ready(Err(error)) if error.kind() == io::ErrorKind::Interrupted => {}
+				Poll::Ready(Err(error)) => return Poll::Ready(Some(Err(error))),
+				// Nothing more has arrived, so a lone escape is the Escape key
+				Poll::Pending => {
+					return match self.decoder.next(true) {
+						Some(input) => Poll::Ready(Some(Ok(input))),
+						None => Poll::Pending,
+					}
+				}
+			}
+		}
+	}
+	fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		self.poll_write_output(cx)
+	}
+	fn size(&self) -> io::Result<(u16, u16)> {
+		Ok(self.size)
+	}
+	fn enable_raw_mode(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+	fn disable_raw_mode(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+/// Backend that keeps its input and output in memory, for tests and for embedding in other interfaces.
+/// Clones share their state, so a clone can feed input to and read the output of a `Readline` using the backend.
+#[derive(Clone)]
+pub struct MemoryBackend {
+	state: Arc<Mutex<MemoryState>>,
+}
+struct MemoryState {
+	input: VecDeque<InputEvent>,
+	output: Vec<u8>,
+	size: (u16, u16),
+	raw_mode: bool,
+	closed: bool,
+	waker: Option<Waker>,
+}
+impl MemoryBackend {
+	/// Create a backend for a terminal with the given number of columns and rows
+	pub fn new(size: (u16, u16)) -> Self {
+		Self {
+			state: Arc::new(Mutex::new(MemoryState {
+				input: VecDeque::new(),
+				output: Vec::new(),
+				size,
+				raw_mode: false,
+				closed: false,
+				waker: None,
+			})),
+		}
+	}
+	fn state(&self) -> MutexGuard<'_, MemoryState> {
+		self.state.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+	fn push(&self, input: InputEvent) {
+		let mut state = self.state();
+		state.input.push_back(input);
+		if let Some(waker) = state.waker.take() {
+			waker.wake();
+		}
+	}
+	pub fn push_event(&self, event: Event) {
+		self.push(InputEvent::Event(event));
+	}
+	pub fn push_key(&self, code: KeyCode, modifiers: KeyModifiers) {
+		self.push_event(Event::Key(KeyEvent::new(code, modifiers)));
+	}
+	/// Type text key by key, with newlines pressing Enter
+	pub fn push_str(&self, text: &str) {
+		for c in text.chars() {
+			match c {
+				'\n' => self.push_key(KeyCode::Enter, KeyModifiers::NONE),
+				c => self.push_key(KeyCode::Char(c), KeyModifiers::NONE),
+			}
+		}
+	}
+	pub fn push_paste(&self, text: impl Into<String>) {
+		self.push(InputEvent::Paste(text.into()));
+	}
+	/// Change the size of the terminal and report it as a resize event
+	pub fn resize(&self, columns: u16, rows: u16) {
+		self.state().size = (columns, rows);
+		self.push_event(Event::Resize(columns, rows));
+	}
+	/// End the input once the events pushed so far have been read
+	pub fn close(&self) {
+		let mut state = self.state();
+		state.closed = true;
+		if let Some(waker) = state.waker.take() {
+			waker.wake();
+		}
+	}
+	/// Take everything written to the backend so far
+	pub fn take_output(&self) -> Vec<u8> {
+		std::mem::take(&mut self.state().output)
+	}
+	pub fn is_raw_mode(&self) -> bool {
+		self.state().raw_mode
+	}
+}
+impl Write for MemoryBackend {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.state().output.extend_from_slice(buf);
+		Ok(buf.len())
+	}
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+impl Backend for MemoryBackend {
+	fn poll_event(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<InputEvent>>> {
+		let mut state = self.state();
+		match state.input.pop_front() {
+			Some(input) => Poll::Ready(Some(Ok(input))),
+			None if state.closed => Poll::Ready(None),
+			None => {
+				state.waker = Some(cx.waker().clone());
+				Poll::Pending
+			}
+		}
+	}
+	fn size(&self) -> io::Result<(u16, u16)> {
+		Ok(self.state().size)
+	}
+	fn enable_raw_mode(&mut self) -> io::Result<()> {
+		self.state().raw_mode = true;
+		Ok(())
+	}
+	fn disable_raw_mode(&mut self) -> io::Result<()> {
+		self.state().raw_mode = false;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use futures::{stream, task::noop_waker_ref};
+
+	use super::*;
+
+	#[test]
+	fn crossterm_pastes_keep_their_newlines() {
+		let key = |code, kind| KeyEvent::new_with_kind(code, KeyModifiers::NONE, kind);
+		let events = [
+			Event::Key(key(KeyCode::Char('a'), KeyEventKind::Press)),
+			Event::Key(key(KeyCode::Char('a'), KeyEventKind::Release)),
+			Event::Paste("select 1;\rselect 2;\r".to_owned()),
+			Event::Key(key(KeyCode::Enter, KeyEventKind::Press)),
+		];
+		let mut backend = CrosstermBackend::with_events(stream::iter(events.map(Ok)));
+		let mut cx = Context::from_waker(noop_waker_ref());
+		let mut received = Vec::new();
+		while let Poll::Ready(Some(event)) = backend.poll_event(&mut cx) {
+			received.push(event.unwrap());
+		}
+		assert_eq!(
+			received,
+			[
+				InputEvent::Event(Event::Key(KeyEvent::new(
+					KeyCode::Char('a'),
+					KeyModifiers::NONE
+				))),
+				InputEvent::Paste("select 1;\rselect 2;\r".to_owned()),
+				InputEvent::Event(Event::Key(KeyEvent::new(
+					KeyCode::Enter,
+					KeyModifiers::NONE
+				))),
+			]
+		);
+	}
+}
@@ -0,0 +1,97 @@
+use std::ops::Range;
+
+use futures::{future::BoxFuture, prelude::*};
+
+/// A candidate returned by a [`Completer`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+	/// Range of graphemes in the line that is replaced by this candidate
+	pub range: Range<usize>,
+	/// Text inserted in place of the range
+	pub replacement: String,
+	/// Text shown when listing candidates, defaults to the replacement
+	pub display: Option<String>,
+}
+impl Completion {
+	pub fn new(range: Range<usize>, replacement: impl Into<String>) -> Self {
+		Self {
+			range,
+			replacement: replacement.into(),
+			display: None,
+		}
+	}
+	/// Set the text shown when listing candidates
+	pub fn with_display(mut self, display: impl Into<String>) -> Self {
+		self.display = Some(display.into());
+		self
+	}
+	/// Text shown when listing candidates
+	pub fn display(&self) -> &str {
+		self.display.as_deref().unwrap_or(&self.replacement)
+	}
+}
+
+/// Source of tab completions, installed with `Readline::set_completer`
+pub trait Completer: Send {
+	/// Return the candidates for completing `line` with the cursor at grapheme index `pos`
+	fn complete(&mut self, line: &str, pos: usize) -> Vec<Completion>;
+}
+impl<F> Completer for F
+where
+	F: FnMut(&str, usize) -> Vec<Completion> + Send,
+{
+	fn complete(&mut self, line: &str, pos: usize) -> Vec<Completion> {
+		self(line, pos)
+	}
+}
+
+/// Source of tab completions that may take a while to resolve, installed with `Readline::set_async_completer`.
+/// Input and output keep being processed while the returned future resolves,
+/// and its candidates are discarded if the line was edited in the meantime.
+pub trait AsyncCompleter: Send {
+	/// Return a future resolving to the candidates for completing `line` with the cursor at grapheme index `pos`
+	fn complete(&mut self, line: &str, pos: usize) -> BoxFuture<'static, Vec<Completion>>;
+}
+impl<F, Fut> AsyncCompleter for F
+where
+	F: FnMut(&str, usize) -> Fut + Send,
+	Fut: Future<Output = Vec<Completion>> + Send + 'static,
+{
+	fn complete(&mut self, line: &str, pos: usize) -> BoxFuture<'static, Vec<Completion>> {
+		self(line, pos).boxed()
+	}
+}
+
+/// Runs a synchronous [`Completer`] as an [`AsyncCompleter`] whose future is immediately ready
+pub(crate) struct SyncCompleter<C>(pub C);
+impl<C: Completer> AsyncCompleter for SyncCompleter<C> {
+	fn complete(&mut self, line: &str, pos: usize) -> BoxFuture<'static, Vec<Completion>> {
+		future::ready(self.0.complete(line, pos)).boxed()
+	}
+}
+
+/// Longest common prefix of all candidates' replacements, if they all replace the same range
+pub(crate) fn common_prefix(candidates: &[Completion], ignore_case: bool) -> Option<&str> {
+	let (first, rest) = candidates.split_first()?;
+	let mut prefix = &first.replacement[..];
+	for candidate in rest {
+		if candidate.range != first.range {
+			return None;
+		}
+		// Cut after the last matching character of the prefix, as case insensitively matching ones may differ in length
+		let len = prefix
+			.char_indices()
+			.zip(candidate.replacement.chars())
+			.take_while(|((_, a), b)| {
+				if ignore_case {
+					a.to_lowercase().eq(b.to_lowercase())
+				} else {
+					a == b
+				}
+			})
+			.last()
+			.map_or(0, |((i, a), _)| i + a.len_utf8());
+		prefix = &prefix[..len];
+	}
+	Some(prefix)
+}
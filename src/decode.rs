@@ -0,0 +1,355 @@
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+use crate::InputEvent;
+
+/// Sent by terminals in bracketed paste mode around pasted text
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+/// Incremental decoder of the bytes sent by a terminal into key events,
+/// mirroring the events crossterm reports for them
+#[derive(Default)]
+pub(crate) struct Decoder {
+	input: Vec<u8>,
+	// Text received so far while in a bracketed paste
+	paste: Option<Vec<u8>>,
+}
+impl Decoder {
+	pub fn push(&mut self, bytes: &[u8]) {
+		self.input.extend_from_slice(bytes);
+	}
+	/// Decode the next event from the input pushed so far.
+	/// Unless `complete` is set, input that may be the start of a longer sequence (e.g. a lone escape) is kept until more arrives.
+	pub fn next(&mut self, complete: bool) -> Option<InputEvent> {
+		loop {
+			if let Some(paste) = &mut self.paste {
+				match find(&self.input, PASTE_END) {
+					Some(end) => {
+						paste.extend(self.input.drain(..end));
+						self.input.drain(..PASTE_END.len());
+						let text = String::from_utf8_lossy(paste).into_owned();
+						self.paste = None;
+						return Some(InputEvent::Paste(text));
+					}
+					None => {
+						// Keep what may be the start of the end marker
+						let keep = self.input.len().min(PASTE_END.len() - 1);
+						paste.extend(self.input.drain(..self.input.len() - keep));
+						return None;
+					}
+				}
+			}
+			let (decoded, len) = decode(&self.input, complete)?;
+			self.input.drain(..len);
+			match decoded {
+				Some(key) => return Some(InputEvent::Event(Event::Key(key))),
+				None => self.paste = Some(Vec::new()),
+			}
+		}
+	}
+	/// Text of a paste that was not terminated before the input ended
+	pub fn finish(&mut self) -> Option<InputEvent> {
+		let mut paste = self.paste.take()?;
+		paste.append(&mut self.input);
+		Some(InputEvent::Paste(
+			String::from_utf8_lossy(&paste).into_owned(),
+		))
+	}
+}
+
+/// Decode the characters sent by a terminal into the key events crossterm reports for them
+pub(crate) fn decode_keys(input: &str) -> Vec<KeyEvent> {
+	let mut input = input.as_bytes();
+	let mut keys = Vec::new();
+	while let Some((decoded, len)) = decode(input, true) {
+		keys.extend(decoded);
+		input = &input[len..];
+	}
+	keys
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+	haystack
+		.windows(needle.len())
+		.position(|window| window == needle)
+}
+
+/// Decode the key at the start of the input, returning `None` in place of the key for the start of a paste.
+/// Returns the number of bytes used, or `None` if more input is needed.
+fn decode(input: &[u8], complete: bool) -> Option<(Option<KeyEvent>, usize)> {
+	match input {
+		[] => None,
+		[0x1b] => complete.then(|| (Some(decode_char('\x1b')), 1)),
+		[0x1b, introducer @ (b'[' | b'O'), rest @ ..] => {
+			match rest.iter().position(|b| (0x40..=0x7e).contains(b)) {
+				Some(end) => {
+					let len = end + 3;
+					if &input[..len] == PASTE_START {
+						return Some((None, len));
+					}
+					let key = decode_escape_sequence(&rest[..end], rest[end]);
+					Some((Some(key), len))
+				}
+				// Incomplete sequence, treat it as Alt-[ or Alt-O
+				None if complete => Some((Some(alt(decode_char(*introducer as char))), 2)),
+				None => None,
+			}
+		}
+		[0x1b, 0x1b, ..] => Some((Some(decode_char('\x1b')), 1)),
+		[0x1b, rest @ ..] => {
+			let (c, len) = decode_utf8(rest, complete)?;
+			Some((Some(alt(decode_char(c))), len + 1))
+		}
+		// Telnet style line endings
+		[b'\r', b'\n' | b'\0', ..] => Some((Some(decode_char('\r')), 2)),
+		[b'\r'] if !complete => None,
+		_ => {
+			let (c, len) = decode_utf8(input, complete)?;
+			Some((Some(decode_char(c)), len))
+		}
+	}
+}
+
+/// Decode the UTF-8 character at the start of the input, returning it and its length in bytes
+fn decode_utf8(input: &[u8], complete: bool) -> Option<(char, usize)> {
+	let len = match input.first()? {
+		0x00..=0x7f => 1,
+		0xc0..=0xdf => 2,
+		0xe0..=0xef => 3,
+		0xf0..=0xf7 => 4,
+		_ => return Some((char::REPLACEMENT_CHARACTER, 1)),
+	};
+	if input.len() < len {
+		return complete.then_some((char::REPLACEMENT_CHARACTER, input.len()));
+	}
+	match std::str::from_utf8(&input[..len]) {
+		Ok(text) => text.chars().next().map(|c| (c, len)),
+		Err(_) => Some((char::REPLACEMENT_CHARACTER, 1)),
+	}
+}
+
+fn alt(key: KeyEvent) -> KeyEvent {
+	KeyEvent::new(key.code, key.modifiers | KeyModifiers::ALT)
+}
+
+fn decode_char(c: char) -> KeyEvent {
+	let (code, modifiers) = match c {
+		'\x1b' => (KeyCode::Esc, KeyModifiers::NONE),
+		'\r' => (KeyCode::Enter, KeyModifiers::NONE),
+		'\t' => (KeyCode::Tab, KeyModifiers::NONE),
+		'\x7f' => (KeyCode::Backspace, KeyModifiers::NONE),
+		'\0' => (KeyCode::Char(' '), KeyModifiers::CONTROL),
+		'\x01'..='\x1a' => (
+			KeyCode::Char((c as u8 - 0x1 + b'a') as char),
+			KeyModifiers::CONTROL,
+		),
+		'\x1c'..='\x1f' => (
+			KeyCode::Char((c as u8 - 0x1c + b'4') as char),
+			KeyModifiers::CONTROL,
+		),
+		c if c.is_uppercase() => (KeyCode::Char(c), KeyModifiers::SHIFT),
+		c => (KeyCode::Char(c), KeyModifiers::NONE),
+	};
+	KeyEvent::new(code, modifiers)
+}
+
+/// Decode a CSI (`ESC [`) or SS3 (`ESC O`) sequence from its parameters and final byte
+fn decode_escape_sequence(params: &[u8], end: u8) -> KeyEvent {
+	let mut params = params
+		.split(|b| *b == b';')
+		.map(|param| std::str::from_utf8(param).ok()?.parse::<u8>().ok());
+	let first = params.next().flatten();
+	let modifiers = match params.next().flatten() {
+		Some(modifier) => {
+			let bits = modifier.saturating_sub(1);
+			let mut modifiers = KeyModifiers::NONE;
+			modifiers.set(KeyModifiers::SHIFT, bits & 1 != 0);
+			modifiers.set(KeyModifiers::ALT, bits & 2 != 0);
+			modifiers.set(KeyModifiers::CONTROL, bits & 4 != 0);
+			modifiers
+		}
+		None => KeyModifiers::NONE,
+	};
+	let code = match (end, first) {
+		(b'A', _) => KeyCode::Up,
+		(b'B', _) => KeyCode::Down,
+		(b'C', _) => KeyCode::Right,
+		(b'D', _) => KeyCode::Left,
+		(b'H', _) => KeyCode::Home,
+		(b'F', _) => KeyCode::End,
+		(b'Z', _) => KeyCode::BackTab,
+		(b'P', _) => KeyCode::F(1),
+		(b'Q', _) => KeyCode::F(2),
+		(b'R', _) => KeyCode::F(3),
+		(b'S', _) => KeyCode::F(4),
+		(b'~', Some(1 | 7)) => KeyCode::Home,
+		(b'~', Some(2)) => KeyCode::Insert,
+		(b'~', Some(3)) => KeyCode::Delete,
+		(b'~', Some(4 | 8)) => KeyCode::End,
+		(b'~', Some(5)) => KeyCode::PageUp,
+		(b'~', Some(6)) => KeyCode::PageDown,
+		(b'~', Some(n @ 11..=15)) => KeyCode::F(n - 10),
+		(b'~', Some(n @ 17..=21)) => KeyCode::F(n - 11),
+		(b'~', Some(n @ 23..=24)) => KeyCode::F(n - 12),
+		_ => KeyCode::Null,
+	};
+	let modifiers = match code {
+		KeyCode::BackTab => modifiers | KeyModifiers::SHIFT,
+		_ => modifiers,
+	};
+	KeyEvent::new(code, modifiers)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+		KeyEvent::new(code, modifiers)
+	}
+
+	/// Push each chunk in turn, collecting every event decoded before the next one arrives
+	fn decode_chunks(chunks: &[&[u8]]) -> Vec<InputEvent> {
+		let mut decoder = Decoder::default();
+		let mut events = Vec::new();
+		for chunk in chunks {
+			decoder.push(chunk);
+			events.extend(std::iter::from_fn(|| decoder.next(false)));
+		}
+		events.extend(std::iter::from_fn(|| decoder.next(true)));
+		events.extend(decoder.finish());
+		events
+	}
+
+	fn keys(events: Vec<InputEvent>) -> Vec<KeyEvent> {
+		events
+			.into_iter()
+			.map(|event| match event {
+				InputEvent::Event(Event::Key(key)) => key,
+				event => panic!("expected a key, got {:?}", event),
+			})
+			.collect()
+	}
+
+	#[test]
+	fn csi_with_modifiers() {
+		assert_eq!(
+			decode_keys("\x1b[1;5D\x1b[3;2~\x1b[1;3A\x1b[Z"),
+			[
+				key(KeyCode::Left, KeyModifiers::CONTROL),
+				key(KeyCode::Delete, KeyModifiers::SHIFT),
+				key(KeyCode::Up, KeyModifiers::ALT),
+				key(KeyCode::BackTab, KeyModifiers::SHIFT),
+			]
+		);
+		assert_eq!(
+			decode_keys("\x1bOP"),
+			[key(KeyCode::F(1), KeyModifiers::NONE)]
+		);
+	}
+
+	#[test]
+	fn alt_chars() {
+		assert_eq!(
+			decode_keys("\x1bf\x1bB\x1b\x7f\x1bé"),
+			[
+				key(KeyCode::Char('f'), KeyModifiers::ALT),
+				key(KeyCode::Char('B'), KeyModifiers::ALT | KeyModifiers::SHIFT),
+				key(KeyCode::Backspace, KeyModifiers::ALT),
+				key(KeyCode::Char('é'), KeyModifiers::ALT),
+			]
+		);
+	}
+
+	#[test]
+	fn lone_escape_waits_for_more_input() {
+		let mut decoder = Decoder::default();
+		decoder.push(b"\x1b");
+		assert_eq!(decoder.next(false), None);
+		decoder.push(b"[C");
+		assert_eq!(
+			decoder.next(false),
+			Some(InputEvent::Event(Event::Key(key(
+				KeyCode::Right,
+				KeyModifiers::NONE
+			))))
+		);
+
+		// Nothing more arrives, so it is the Escape key
+		decoder.push(b"\x1b");
+		assert_eq!(decoder.next(false), None);
+		assert_eq!(
+			decoder.next(true),
+			Some(InputEvent::Event(Event::Key(key(
+				KeyCode::Esc,
+				KeyModifiers::NONE
+			))))
+		);
+		assert_eq!(
+			decode_keys("\x1b\x1b["),
+			[
+				key(KeyCode::Esc, KeyModifiers::NONE),
+				key(KeyCode::Char('['), KeyModifiers::ALT),
+			]
+		);
+	}
+
+	#[test]
+	fn utf8_split_across_reads() {
+		let text = "日é".as_bytes();
+		assert_eq!(
+			keys(decode_chunks(&[&text[..1], &text[1..4], &text[4..]])),
+			[
+				key(KeyCode::Char('日'), KeyModifiers::NONE),
+				key(KeyCode::Char('é'), KeyModifiers::NONE),
+			]
+		);
+		// Invalid bytes are replaced
+		assert_eq!(
+			keys(decode_chunks(&[b"\xffa"])),
+			[
+				key(
+					KeyCode::Char(char::REPLACEMENT_CHARACTER),
+					KeyModifiers::NONE
+				),
+				key(KeyCode::Char('a'), KeyModifiers::NONE),
+			]
+		);
+	}
+
+	#[test]
+	fn paste_markers_split_across_reads() {
+		assert_eq!(
+			decode_chunks(&[b"a\x1b[20", b"0~ls\r\n\x1b", b"[201", b"~b"]),
+			[
+				InputEvent::Event(Event::Key(key(KeyCode::Char('a'), KeyModifiers::NONE))),
+				InputEvent::Paste("ls\r\n".to_owned()),
+				InputEvent::Event(Event::Key(key(KeyCode::Char('b'), KeyModifiers::NONE))),
+			]
+		);
+		// Unterminated when the input ends
+		assert_eq!(
+			decode_chunks(&[b"\x1b[200~abc\x1b[20"]),
+			[InputEvent::Paste("abc\x1b[20".to_owned())]
+		);
+	}
+
+	#[test]
+	fn telnet_line_endings() {
+		let enter = key(KeyCode::Enter, KeyModifiers::NONE);
+		assert_eq!(
+			decode_keys("a\r\nb\r\0\r"),
+			[
+				key(KeyCode::Char('a'), KeyModifiers::NONE),
+				enter,
+				key(KeyCode::Char('b'), KeyModifiers::NONE),
+				enter,
+				enter,
+			]
+		);
+		assert_eq!(
+			keys(decode_chunks(&[b"\r", b"\n\r", b"\0"])),
+			[enter, enter]
+		);
+	}
+}
@@ -0,0 +1,49 @@
+use std::{
+	io::{self, Write},
+	ops::Range,
+};
+
+use crossterm::style::ContentStyle;
+
+/// Styles the line while it is being edited, installed with `Readline::set_highlighter`
+pub trait Highlighter: Send {
+	/// Return styled byte ranges of `line`, with the cursor at grapheme index `pos`.
+	/// Text outside of any range is written unstyled, overlapping or out of bounds ranges are ignored.
+	fn highlight(&self, line: &str, pos: usize) -> Vec<(Range<usize>, ContentStyle)>;
+	/// Whether the highlighting depends on the cursor position (e.g. matching brackets),
+	/// in which case the line is redrawn whenever the cursor moves
+	fn depends_on_cursor(&self) -> bool {
+		false
+	}
+}
+impl<F> Highlighter for F
+where
+	F: Fn(&str, usize) -> Vec<(Range<usize>, ContentStyle)> + Send,
+{
+	fn highlight(&self, line: &str, pos: usize) -> Vec<(Range<usize>, ContentStyle)> {
+		self(line, pos)
+	}
+}
+
+/// Write `line` with the given styled ranges applied
+pub(crate) fn write_highlighted(
+	term: &mut impl Write,
+	line: &str,
+	mut spans: Vec<(Range<usize>, ContentStyle)>,
+) -> io::Result<()> {
+	spans.sort_by_key(|(range, _)| range.start);
+	let mut written = 0;
+	for (range, style) in spans {
+		if range.start < written
+			|| range.end > line.len()
+			|| !line.is_char_boundary(range.start)
+			|| !line.is_char_boundary(range.end)
+		{
+			continue;
+		}
+		write!(term, "{}", &line[written..range.start])?;
+		write!(term, "{}", style.apply(&line[range.clone()]))?;
+		written = range.end;
+	}
+	write!(term, "{}", &line[written..])
+}
@@ -0,0 +1,14 @@
+/// Source of the suggestion shown dimmed after the line while the cursor is at its end, installed with `Readline::set_hinter`.
+/// Right, End or Ctrl-F accept the whole suggestion and Alt-F its next word.
+pub trait Hinter: Send {
+	/// Return the text suggested to follow `line`. Suggestions spanning several lines are not shown.
+	fn hint(&self, line: &str) -> Option<String>;
+}
+impl<F> Hinter for F
+where
+	F: Fn(&str) -> Option<String> + Send,
+{
+	fn hint(&self, line: &str) -> Option<String> {
+		self(line)
+	}
+}
@@ -1,72 +1,212 @@
-use std::collections::VecDeque;
-
-use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use std::{
+	collections::VecDeque,
+	fs::{self, File, OpenOptions},
+	io::{self, BufRead, BufReader, BufWriter, Write},
+	path::{Path, PathBuf},
+};
 
 pub struct History {
 	pub entries: VecDeque<String>,
 	pub max_size: usize,
-	pub sender: UnboundedSender<String>,
-	receiver: UnboundedReceiver<String>,
 
 	current_position: Option<usize>,
+	// Line typed before history navigation started, only entries starting with it are visited
+	prefix: String,
+
+	// File that new entries are appended to, and how many entries it holds
+	append_file: Option<PathBuf>,
+	append_file_entries: usize,
 }
 impl Default for History {
 	fn default() -> Self {
-		let (sender, receiver) = mpsc::unbounded();
 		Self {
 			entries: Default::default(),
 			max_size: 1000,
-			sender,
-			receiver,
 			current_position: Default::default(),
+			prefix: String::new(),
+			append_file: None,
+			append_file_entries: 0,
 		}
 	}
 }
 
 impl History {
-	// Update history entries
-	pub async fn update(&mut self) {
-		// Receive all new lines
-		while let Ok(Some(line)) = self.receiver.try_next() {
-			// Don't add entry if last entry was same, or line was empty.
-			if self.entries.front() == Some(&line) || line.is_empty() { continue; }
-			// Add entry to front of history
-			self.entries.push_front(line);
-			// Reset offset to newest entry
-			self.current_position = None;
-			// Check if already have enough entries
-			if self.entries.len() > self.max_size {
-				// Remove oldest entry
-				self.entries.pop_back();
+	/// Add an entry, appending it to the append file if one is set.
+	/// The entry is kept even if writing it fails, the error is returned afterwards.
+	pub fn add(&mut self, line: String) -> io::Result<()> {
+		// Don't add entry if last entry was same, or line was empty.
+		if self.entries.front() == Some(&line) || line.is_empty() {
+			return Ok(());
+		}
+		let appended = self.append_to_file(&line);
+		self.push(line);
+		appended
+	}
+	// Add entry to front of history, dropping the oldest entry if full
+	fn push(&mut self, line: String) {
+		self.entries.push_front(line);
+		// Reset offset to newest entry
+		self.current_position = None;
+		// Check if already have enough entries
+		if self.entries.len() > self.max_size {
+			// Remove oldest entry
+			self.entries.pop_back();
+		}
+	}
+
+	/// Load entries from a history file, oldest first
+	pub fn load(&mut self, path: &Path) -> io::Result<()> {
+		for line in BufReader::new(File::open(path)?).lines() {
+			let line = unescape(&line?);
+			if self.entries.front() == Some(&line) || line.is_empty() {
+				continue;
+			}
+			self.push(line);
+		}
+		Ok(())
+	}
+	/// Write all entries to a history file, replacing its contents
+	pub fn save(&mut self, path: &Path) -> io::Result<()> {
+		let mut file = BufWriter::new(File::create(path)?);
+		for entry in self.entries.iter().rev() {
+			writeln!(file, "{}", escape(entry))?;
+		}
+		file.flush()?;
+		if self.append_file.as_deref() == Some(path) {
+			self.append_file_entries = self.entries.len();
+		}
+		Ok(())
+	}
+	/// Append every new entry to the given file, or stop appending if `None`
+	pub fn set_append_file(&mut self, path: Option<PathBuf>) -> io::Result<()> {
+		self.append_file_entries = 0;
+		if let Some(path) = path.as_deref().filter(|path| path.exists()) {
+			for line in BufReader::new(File::open(path)?).lines() {
+				line?;
+				self.append_file_entries += 1;
 			}
 		}
+		self.append_file = path;
+		Ok(())
 	}
+	fn append_to_file(&mut self, line: &str) -> io::Result<()> {
+		let path = match &self.append_file {
+			Some(path) => path,
+			None => return Ok(()),
+		};
+		let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+		writeln!(file, "{}", escape(line))?;
+		self.append_file_entries += 1;
 
-	// Find next history that matches a given string from an index
-	pub fn search_next(&mut self, _current: &str) -> Option<&str> {
-		if let Some(index) = &mut self.current_position {
-			if *index < self.entries.len() - 1 {
-				*index += 1;
+		// Drop the oldest lines of the file once it grows past the maximum history size
+		if self.append_file_entries > self.max_size {
+			let contents = fs::read_to_string(path)?;
+			let lines = contents.lines().collect::<Vec<_>>();
+			let keep = &lines[lines.len().saturating_sub(self.max_size)..];
+			let mut file = BufWriter::new(File::create(path)?);
+			for line in keep {
+				writeln!(file, "{}", line)?;
 			}
-			Some(&self.entries[*index])
-		} else if !self.entries.is_empty() {
-			self.current_position = Some(0);
-			Some(&self.entries[0])
-		} else {
-			None
+			file.flush()?;
+			self.append_file_entries = keep.len();
 		}
+		Ok(())
+	}
+
+	// Forget the current navigation position, so that the next search starts from the newest entry again
+	pub fn reset_position(&mut self) {
+		self.current_position = None;
 	}
-	// Find previous history item that matches a given string from an index
-	pub fn search_previous(&mut self, _current: &str) -> Option<&str> {
-		if let Some(index) = &mut self.current_position {
-			if *index == 0 {
+	/// Rest of the most recent entry that starts with `line` and is longer than it
+	pub fn hint(&self, line: &str) -> Option<&str> {
+		self.entries
+			.iter()
+			.find(|entry| entry.len() > line.len() && entry.starts_with(line))
+			.map(|entry| &entry[line.len()..])
+	}
+	// Find next (older) history entry that starts with the line typed before navigation began
+	pub fn search_next(&mut self, current: &str) -> Option<&str> {
+		let start = match self.current_position {
+			Some(index) => index + 1,
+			None => {
+				self.prefix = current.to_owned();
+				0
+			}
+		};
+		let index = (start..self.entries.len())
+			.find(|&i| self.entries[i].starts_with(&self.prefix) && self.entries[i] != current)?;
+		self.current_position = Some(index);
+		Some(&self.entries[index])
+	}
+	// Find previous (newer) history entry matching the prefix, or the prefix itself once past the newest match
+	pub fn search_previous(&mut self, current: &str) -> Option<&str> {
+		let index = self.current_position?;
+		match (0..index)
+			.rev()
+			.find(|&i| self.entries[i].starts_with(&self.prefix) && self.entries[i] != current)
+		{
+			Some(index) => {
+				self.current_position = Some(index);
+				Some(&self.entries[index])
+			}
+			None => {
 				self.current_position = None;
-				return Some("");
+				Some(&self.prefix)
 			}
-			*index -= 1;
-			Some(&self.entries[*index])
+		}
+	}
+	// Find the first entry containing `query`, walking from index `start` towards older (or newer) entries.
+	// Returns the index of the entry and the byte offset of the match within it.
+	pub fn search_substring(
+		&self,
+		query: &str,
+		start: usize,
+		reverse: bool,
+	) -> Option<(usize, usize)> {
+		let find = |i: usize| self.entries[i].find(query).map(|pos| (i, pos));
+		if reverse {
+			(start..self.entries.len()).find_map(find)
 		} else {
-			None
+			(0..=start.min(self.entries.len().checked_sub(1)?))
+				.rev()
+				.find_map(find)
+		}
+	}
+}
+
+/// Escape an entry so that it fits on a single line of the history file.
+/// Backslashes are doubled and newlines / carriage returns become `\n` / `\r`.
+fn escape(entry: &str) -> String {
+	let mut escaped = String::with_capacity(entry.len());
+	for c in entry.chars() {
+		match c {
+			'\\' => escaped.push_str("\\\\"),
+			'\n' => escaped.push_str("\\n"),
+			'\r' => escaped.push_str("\\r"),
+			c => escaped.push(c),
+		}
+	}
+	escaped
+}
+/// Reverse of [`escape`]. Unknown escapes are kept verbatim.
+fn unescape(line: &str) -> String {
+	let mut unescaped = String::with_capacity(line.len());
+	let mut chars = line.chars();
+	while let Some(c) = chars.next() {
+		if c != '\\' {
+			unescaped.push(c);
+			continue;
+		}
+		match chars.next() {
+			Some('\\') => unescaped.push('\\'),
+			Some('n') => unescaped.push('\n'),
+			Some('r') => unescaped.push('\r'),
+			Some(c) => {
+				unescaped.push('\\');
+				unescaped.push(c);
+			}
+			None => unescaped.push('\\'),
 		}
 	}
+	unescaped
 }
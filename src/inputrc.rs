@@ -0,0 +1,455 @@
+use std::{
+	fmt,
+	io::{self, Write},
+	str::FromStr,
+};
+
+use crossterm::event::KeyEvent;
+
+use crate::{decode::decode_keys, line::LineState, EditCommand};
+
+/// Line of an inputrc file that was skipped, e.g. because it uses an unsupported directive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputrcWarning {
+	/// Line number, starting at 1
+	pub line: usize,
+	pub message: String,
+}
+impl fmt::Display for InputrcWarning {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "line {}: {}", self.line, self.message)
+	}
+}
+
+/// Apply the key bindings and settings of an inputrc file to the line editor.
+/// `$if` blocks naming an application apply only if it matches `application`.
+pub(crate) fn apply(
+	contents: &str,
+	application: &str,
+	line_state: &mut LineState,
+	term: &mut impl Write,
+) -> io::Result<Vec<InputrcWarning>> {
+	let mut warnings = Vec::new();
+	// Whether each enclosing `$if` branch is taken
+	let mut conditions: Vec<bool> = Vec::new();
+
+	for (number, line) in contents.lines().enumerate() {
+		let mut warn = |message: String| {
+			warnings.push(InputrcWarning {
+				line: number + 1,
+				message,
+			})
+		};
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+
+		// Lines within branches that aren't taken are skipped without being checked
+		let active = conditions.iter().all(|taken| *taken);
+		if let Some(directive) = line.strip_prefix('$') {
+			let (name, argument) = split_word(directive);
+			match &name.to_ascii_lowercase()[..] {
+				"if" if !active => conditions.push(false),
+				"if" => {
+					let taken = match argument.split_once('=') {
+						Some((variable, value)) => match variable.trim() {
+							"mode" => value.trim() == edit_mode_name(line_state),
+							"term" => std::env::var("TERM").is_ok_and(|term| {
+								let value = value.trim();
+								term == value || term.starts_with(&format!("{}-", value))
+							}),
+							variable => {
+								warn(format!("unsupported $if condition: {}", variable));
+								false
+							}
+						},
+						None => argument.eq_ignore_ascii_case(application),
+					};
+					conditions.push(taken);
+				}
+				"else" => match conditions.last_mut() {
+					Some(taken) => *taken = !*taken,
+					None => warn("$else without $if".to_owned()),
+				},
+				"endif" => {
+					if conditions.pop().is_none() {
+						warn("$endif without $if".to_owned());
+					}
+				}
+				_ if !active => {}
+				_ => warn(format!("unsupported directive: ${}", name)),
+			}
+			continue;
+		}
+		if !active {
+			continue;
+		}
+
+		let (word, argument) = split_word(line);
+		if word.eq_ignore_ascii_case("set") {
+			let (variable, value) = split_word(argument);
+			match &variable.to_ascii_lowercase()[..] {
+				"editing-mode" => match value {
+					#[cfg(feature = "vi")]
+					"emacs" => line_state.set_edit_mode(crate::EditMode::Emacs, term)?,
+					#[cfg(not(feature = "vi"))]
+					"emacs" => {}
+					#[cfg(feature = "vi")]
+					"vi" => line_state.set_edit_mode(crate::EditMode::Vi, term)?,
+					#[cfg(not(feature = "vi"))]
+					"vi" => warn("vi mode requires the `vi` feature".to_owned()),
+					value => warn(format!("unknown editing mode: {}", value)),
+				},
+				"completion-ignore-case" => match parse_bool(value) {
+					Some(value) => line_state.completion_ignore_case = value,
+					None => warn(format!("invalid value for {}: {}", variable, value)),
+				},
+				_ => warn(format!("unsupported variable: {}", variable)),
+			}
+			continue;
+		}
+
+		match parse_binding(line) {
+			Ok((keys, function)) => match EditCommand::from_str(function) {
+				Ok(command) => {
+					line_state.keymap.bind(&keys, command);
+				}
+				Err(error) => warn(error.to_string()),
+			},
+			Err(message) => warn(message),
+		}
+	}
+	if !conditions.is_empty() {
+		warnings.push(InputrcWarning {
+			line: contents.lines().count(),
+			message: "missing $endif".to_owned(),
+		});
+	}
+	// Setting the editing mode redraws the line
+	term.flush()?;
+	Ok(warnings)
+}
+
+/// Split off the first whitespace delimited word
+fn split_word(text: &str) -> (&str, &str) {
+	let text = text.trim();
+	match text.split_once(char::is_whitespace) {
+		Some((word, rest)) => (word, rest.trim()),
+		None => (text, ""),
+	}
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+	match &value.to_ascii_lowercase()[..] {
+		"on" | "1" => Some(true),
+		"off" | "0" => Some(false),
+		_ => None,
+	}
+}
+
+#[cfg(feature = "vi")]
+fn edit_mode_name(line_state: &LineState) -> &'static str {
+	match line_state.edit_mode() {
+		crate::EditMode::Emacs => "emacs",
+		crate::EditMode::Vi => "vi",
+	}
+}
+#[cfg(not(feature = "vi"))]
+fn edit_mode_name(_line_state: &LineState) -> &'static str {
+	"emacs"
+}
+
+/// Parse a `keyseq: function-name` or `keyname: function-name` line
+fn parse_binding(line: &str) -> Result<(Vec<KeyEvent>, &str), String> {
+	let (input, rest) = match line.strip_prefix('"') {
+		Some(quoted) => {
+			let (input, rest) = parse_quoted(quoted)?;
+			let rest = rest
+				.trim_start()
+				.strip_prefix(':')
+				.ok_or_else(|| format!("expected ':' after key sequence: {}", line))?;
+			(input, rest)
+		}
+		None => {
+			let (name, rest) = line
+				.split_once(':')
+				.ok_or_else(|| format!("unknown directive: {}", line))?;
+			(parse_key_name(name.trim())?, rest)
+		}
+	};
+	let function = rest.trim();
+	if function.starts_with(['"', '\'']) {
+		return Err(format!("macros are not supported: {}", line));
+	}
+	if function.is_empty() {
+		return Err(format!("missing function name: {}", line));
+	}
+	Ok((decode_keys(&input.iter().collect::<String>()), function))
+}
+
+/// Parse a key sequence up to its closing quote into the characters the terminal sends,
+/// returning them along with the rest of the line
+fn parse_quoted(text: &str) -> Result<(Vec<char>, &str), String> {
+	let mut input = Vec::new();
+	let mut chars = text.char_indices().peekable();
+	while let Some((i, c)) = chars.next() {
+		match c {
+			'"' => return Ok((input, &text[i + 1..])),
+			'\\' => {
+				let (_, escaped) = chars
+					.next()
+					.ok_or_else(|| "unterminated key sequence".to_owned())?;
+				match escaped {
+					'C' | 'M' if chars.next_if(|(_, c)| *c == '-').is_some() => {
+						let (_, key) = chars
+							.next()
+							.ok_or_else(|| "unterminated key sequence".to_owned())?;
+						match escaped {
+							'C' => input.push(control(key)),
+							_ => input.extend(['\x1b', key]),
+						}
+					}
+					'e' => input.push('\x1b'),
+					'a' => input.push('\x07'),
+					'b' => input.push('\x08'),
+					'd' => input.push('\x7f'),
+					'f' => input.push('\x0c'),
+					'n' => input.push('\n'),
+					'r' => input.push('\r'),
+					't' => input.push('\t'),
+					'v' => input.push('\x0b'),
+					'0'..='7' => {
+						let mut code = escaped.to_digit(8).unwrap_or(0);
+						for _ in 0..2 {
+							match chars.next_if(|(_, c)| c.is_digit(8)) {
+								Some((_, digit)) => {
+									code = code * 8 + digit.to_digit(8).unwrap_or(0)
+								}
+								None => break,
+							}
+						}
+						input.extend(char::from_u32(code));
+					}
+					'x' => {
+						let mut code = 0;
+						for _ in 0..2 {
+							match chars.next_if(|(_, c)| c.is_ascii_hexdigit()) {
+								Some((_, digit)) => {
+									code = code * 16 + digit.to_digit(16).unwrap_or(0)
+								}
+								None => break,
+							}
+						}
+						input.extend(char::from_u32(code));
+					}
+					escaped => input.push(escaped),
+				}
+			}
+			c => input.push(c),
+		}
+	}
+	Err("unterminated key sequence".to_owned())
+}
+
+/// Parse a key name like `Control-u` or `Meta-Rubout` into the characters the terminal sends
+fn parse_key_name(name: &str) -> Result<Vec<char>, String> {
+	let (mut ctrl, mut meta) = (false, false);
+	let mut rest = name;
+	loop {
+		let lower = rest.to_ascii_lowercase();
+		if let Some(prefix) = ["control-", "c-"].iter().find(|p| lower.starts_with(*p)) {
+			ctrl = true;
+			rest = &rest[prefix.len()..];
+		} else if let Some(prefix) = ["meta-", "m-"].iter().find(|p| lower.starts_with(*p)) {
+			meta = true;
+			rest = &rest[prefix.len()..];
+		} else {
+			break;
+		}
+	}
+	let key = match &rest.to_ascii_lowercase()[..] {
+		"rubout" | "del" => '\x7f',
+		"escape" | "esc" => '\x1b',
+		"newline" | "lfd" => '\n',
+		"return" | "ret" => '\r',
+		"space" | "spc" => ' ',
+		"tab" => '\t',
+		_ => {
+			let mut chars = rest.chars();
+			match (chars.next(), chars.next()) {
+				(Some(c), None) => c,
+				_ => return Err(format!("unknown key name: {}", name)),
+			}
+		}
+	};
+	let key = if ctrl { control(key) } else { key };
+	Ok(if meta { vec!['\x1b', key] } else { vec![key] })
+}
+
+/// Character sent for a key pressed with Control
+fn control(key: char) -> char {
+	match key {
+		'?' => '\x7f',
+		key if key.is_ascii() => ((key.to_ascii_lowercase() as u8) & 0x1f) as char,
+		key => key,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crossterm::event::{KeyCode, KeyModifiers};
+
+	use super::*;
+	use crate::Binding;
+
+	fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+		KeyEvent::new(code, modifiers)
+	}
+
+	fn apply_to_new_line(contents: &str) -> (LineState, Vec<InputrcWarning>) {
+		let mut line_state = LineState::new("> ".to_owned(), (80, 24));
+		let warnings = apply(contents, "test", &mut line_state, &mut Vec::new()).unwrap();
+		(line_state, warnings)
+	}
+
+	fn command(line_state: &mut LineState, keys: &[KeyEvent]) -> Option<EditCommand> {
+		match line_state.keymap.get_mut(keys)? {
+			Binding::Command(command) => Some(*command),
+			Binding::Callback(_) => None,
+		}
+	}
+
+	#[test]
+	fn quoted_escapes() {
+		let ctrl_x = key(KeyCode::Char('x'), KeyModifiers::CONTROL);
+		let (keys, function) = parse_binding(r#""\C-x\C-e": kill-line"#).unwrap();
+		assert_eq!(
+			keys,
+			[ctrl_x, key(KeyCode::Char('e'), KeyModifiers::CONTROL)]
+		);
+		assert_eq!(function, "kill-line");
+		let (keys, _) = parse_binding(r#""\M-f\e[1;5C": forward-word"#).unwrap();
+		assert_eq!(
+			keys,
+			[
+				key(KeyCode::Char('f'), KeyModifiers::ALT),
+				key(KeyCode::Right, KeyModifiers::CONTROL),
+			]
+		);
+		// Octal and hex codes, and quoted characters
+		let (keys, _) = parse_binding(r#""\030\x65\"\\" : undo"#).unwrap();
+		assert_eq!(
+			keys,
+			[
+				ctrl_x,
+				key(KeyCode::Char('e'), KeyModifiers::NONE),
+				key(KeyCode::Char('"'), KeyModifiers::NONE),
+				key(KeyCode::Char('\\'), KeyModifiers::NONE),
+			]
+		);
+		assert!(parse_binding(r#""\C-x: undo"#).is_err());
+	}
+
+	#[test]
+	fn key_names() {
+		let (keys, _) = parse_binding("Control-u: unix-line-discard").unwrap();
+		assert_eq!(keys, [key(KeyCode::Char('u'), KeyModifiers::CONTROL)]);
+		let (keys, _) = parse_binding("Meta-Rubout: backward-kill-word").unwrap();
+		assert_eq!(keys, [key(KeyCode::Backspace, KeyModifiers::ALT)]);
+		let (keys, _) = parse_binding("C-M-h: backward-kill-word").unwrap();
+		assert_eq!(
+			keys,
+			[key(
+				KeyCode::Char('h'),
+				KeyModifiers::CONTROL | KeyModifiers::ALT
+			)]
+		);
+		let (keys, _) = parse_binding("TAB: complete").unwrap();
+		assert_eq!(keys, [key(KeyCode::Tab, KeyModifiers::NONE)]);
+		assert!(parse_binding("Hyper-x: undo").is_err());
+	}
+
+	#[test]
+	fn rejects_macros() {
+		let (mut line_state, warnings) = apply_to_new_line("\"\\C-xd\": \"date\\n\"\nC-t: 'x'");
+		assert_eq!(
+			warnings
+				.iter()
+				.map(|warning| warning.line)
+				.collect::<Vec<_>>(),
+			[1, 2]
+		);
+		assert!(warnings[0].message.starts_with("macros are not supported"));
+		let ctrl_x = key(KeyCode::Char('x'), KeyModifiers::CONTROL);
+		let d = key(KeyCode::Char('d'), KeyModifiers::NONE);
+		assert_eq!(command(&mut line_state, &[ctrl_x, d]), None);
+	}
+
+	#[test]
+	fn nested_conditions() {
+		let contents = "\
+$if test
+	$if mode=emacs
+		C-o: undo
+	$else
+		C-o: kill-line
+	$endif
+$else
+	C-p: undo
+	$if mode=vi
+		C-p: kill-line
+	$endif
+$endif
+$if other
+	C-q: undo
+$endif
+";
+		let (mut line_state, warnings) = apply_to_new_line(contents);
+		assert_eq!(warnings, []);
+		let ctrl = |c| key(KeyCode::Char(c), KeyModifiers::CONTROL);
+		assert_eq!(
+			command(&mut line_state, &[ctrl('o')]),
+			Some(EditCommand::Undo)
+		);
+		assert_ne!(
+			command(&mut line_state, &[ctrl('p')]),
+			Some(EditCommand::Undo)
+		);
+		assert_eq!(command(&mut line_state, &[ctrl('q')]), None);
+	}
+
+	#[test]
+	fn warns_with_line_numbers() {
+		let contents = "\
+# comment
+
+set bell-style none
+C-o: no-such-command
+$if version >= 8
+$endif
+$if other
+	$if version >= 8
+	$endif
+	$include /etc/inputrc
+$endif
+$endif
+$if test
+";
+		let (_, warnings) = apply_to_new_line(contents);
+		let lines = warnings
+			.iter()
+			.map(|warning| (warning.line, &warning.message[..]))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			lines,
+			[
+				(3, "unsupported variable: bell-style"),
+				(4, "unknown command: no-such-command"),
+				(5, "unsupported $if condition: version >"),
+				(12, "$endif without $if"),
+				(13, "missing $endif"),
+			]
+		);
+	}
+}
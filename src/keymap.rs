@@ -0,0 +1,294 @@
+use std::{collections::HashMap, str::FromStr};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use thiserror::Error;
+
+/// Named editing command that keys can be bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EditCommand {
+	/// Submit the line, or start a new line if the validator deems it incomplete
+	AcceptLine,
+	/// Return `ReadlineError::Interrupted`, discarding the line
+	Interrupt,
+	/// Return `ReadlineError::Eof`
+	EndOfFile,
+	/// Delete the grapheme under the cursor, or return `ReadlineError::Eof` if the line is empty
+	DeleteCharOrEndOfFile,
+	/// Delete the grapheme under the cursor
+	DeleteChar,
+	BackwardDeleteChar,
+	BackwardChar,
+	ForwardChar,
+	/// Move to the previous whitespace delimited word
+	BackwardWord,
+	/// Move to the next whitespace delimited word
+	ForwardWord,
+	BeginningOfLine,
+	EndOfLine,
+	/// Move up in multi-line input, or to the previous history entry starting with the text typed so far
+	HistorySearchBackward,
+	/// Move down in multi-line input, or to the next history entry starting with the text typed so far
+	HistorySearchForward,
+	ReverseSearchHistory,
+	ForwardSearchHistory,
+	/// Abort an incremental history search, restoring the line, or end the current completion
+	Abort,
+	Complete,
+	CompleteBackward,
+	ClearScreen,
+	/// Kill from the start of the line to the cursor
+	UnixLineDiscard,
+	/// Kill from the cursor to the end of the line
+	KillLine,
+	/// Kill the whitespace delimited word before the cursor
+	UnixWordRubout,
+	/// Kill the alphanumeric word after the cursor
+	KillWord,
+	/// Kill the alphanumeric word before the cursor
+	BackwardKillWord,
+	Yank,
+	YankPop,
+	Undo,
+	Redo,
+}
+
+/// Names of the commands as used by GNU readline, plus a few aliases
+const COMMAND_NAMES: &[(&str, EditCommand)] = &[
+	("accept-line", EditCommand::AcceptLine),
+	("interrupt", EditCommand::Interrupt),
+	("end-of-file", EditCommand::EndOfFile),
+	(
+		"delete-char-or-end-of-file",
+		EditCommand::DeleteCharOrEndOfFile,
+	),
+	("delete-char", EditCommand::DeleteChar),
+	("backward-delete-char", EditCommand::BackwardDeleteChar),
+	("backward-char", EditCommand::BackwardChar),
+	("forward-char", EditCommand::ForwardChar),
+	("backward-word", EditCommand::BackwardWord),
+	("forward-word", EditCommand::ForwardWord),
+	("beginning-of-line", EditCommand::BeginningOfLine),
+	("end-of-line", EditCommand::EndOfLine),
+	(
+		"history-search-backward",
+		EditCommand::HistorySearchBackward,
+	),
+	("history-search-forward", EditCommand::HistorySearchForward),
+	("previous-history", EditCommand::HistorySearchBackward),
+	("next-history", EditCommand::HistorySearchForward),
+	("reverse-search-history", EditCommand::ReverseSearchHistory),
+	("forward-search-history", EditCommand::ForwardSearchHistory),
+	("abort", EditCommand::Abort),
+	("complete", EditCommand::Complete),
+	("menu-complete", EditCommand::Complete),
+	("menu-complete-backward", EditCommand::CompleteBackward),
+	("clear-screen", EditCommand::ClearScreen),
+	("unix-line-discard", EditCommand::UnixLineDiscard),
+	("kill-line", EditCommand::KillLine),
+	("unix-word-rubout", EditCommand::UnixWordRubout),
+	("kill-word", EditCommand::KillWord),
+	("backward-kill-word", EditCommand::BackwardKillWord),
+	("yank", EditCommand::Yank),
+	("yank-pop", EditCommand::YankPop),
+	("undo", EditCommand::Undo),
+	("redo", EditCommand::Redo),
+];
+
+/// Error returned when parsing an unknown command name
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("unknown command: {0}")]
+pub struct UnknownCommand(pub String);
+
+impl FromStr for EditCommand {
+	type Err = UnknownCommand;
+	/// Parse a command by its GNU readline name, e.g. `history-search-backward`
+	fn from_str(name: &str) -> Result<Self, Self::Err> {
+		COMMAND_NAMES
+			.iter()
+			.find(|(command_name, _)| command_name.eq_ignore_ascii_case(name))
+			.map(|(_, command)| *command)
+			.ok_or_else(|| UnknownCommand(name.to_owned()))
+	}
+}
+
+/// Action run by a key binding
+pub enum Binding {
+	Command(EditCommand),
+	/// Application callback, which may edit the line
+	Callback(Box<dyn FnMut(&mut LineBuffer) + Send>),
+}
+impl Binding {
+	pub fn callback(callback: impl FnMut(&mut LineBuffer) + Send + 'static) -> Self {
+		Binding::Callback(Box::new(callback))
+	}
+}
+impl From<EditCommand> for Binding {
+	fn from(command: EditCommand) -> Self {
+		Binding::Command(command)
+	}
+}
+
+/// Line being edited, passed to callbacks bound with `Readline::bind`.
+/// Changes are applied and redrawn once the callback returns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineBuffer {
+	pub line: String,
+	/// Grapheme index of the cursor, moved to the end of the line if out of bounds
+	pub cursor: usize,
+}
+
+/// Whether a key sequence is bound, possibly as the start of longer sequences
+pub(crate) enum Lookup {
+	Bound,
+	Prefix,
+	Unbound,
+}
+
+/// Bindings from key sequences to editing commands or callbacks.
+/// Keys pressed with Shift use the binding of the key without Shift, unless they are bound themselves.
+pub struct Keymap {
+	bindings: HashMap<Vec<KeyEvent>, Binding>,
+}
+impl Keymap {
+	/// Create a keymap with the default bindings
+	pub fn new() -> Self {
+		let mut keymap = Self::empty();
+		let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+		let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+		let defaults = [
+			(key(KeyCode::Enter), EditCommand::AcceptLine),
+			(key(KeyCode::Backspace), EditCommand::BackwardDeleteChar),
+			(key(KeyCode::Delete), EditCommand::DeleteChar),
+			(key(KeyCode::Left), EditCommand::BackwardChar),
+			(key(KeyCode::Right), EditCommand::ForwardChar),
+			(key(KeyCode::Home), EditCommand::BeginningOfLine),
+			(key(KeyCode::End), EditCommand::EndOfLine),
+			(key(KeyCode::Up), EditCommand::HistorySearchBackward),
+			(key(KeyCode::Down), EditCommand::HistorySearchForward),
+			(key(KeyCode::Tab), EditCommand::Complete),
+			(key(KeyCode::BackTab), EditCommand::CompleteBackward),
+			(ctrl('d'), EditCommand::DeleteCharOrEndOfFile),
+			(ctrl('c'), EditCommand::Interrupt),
+			(ctrl('r'), EditCommand::ReverseSearchHistory),
+			(ctrl('s'), EditCommand::ForwardSearchHistory),
+			(ctrl('g'), EditCommand::Abort),
+			(ctrl('l'), EditCommand::ClearScreen),
+			(ctrl('u'), EditCommand::UnixLineDiscard),
+			(
+				KeyEvent::new(KeyCode::Left, KeyModifiers::CONTROL),
+				EditCommand::BackwardWord,
+			),
+			(
+				KeyEvent::new(KeyCode::Right, KeyModifiers::CONTROL),
+				EditCommand::ForwardWord,
+			),
+			(
+				KeyEvent::new(KeyCode::Backspace, KeyModifiers::CONTROL),
+				EditCommand::BackwardKillWord,
+			),
+			(
+				KeyEvent::new(KeyCode::Backspace, KeyModifiers::ALT),
+				EditCommand::BackwardKillWord,
+			),
+			(
+				KeyEvent::new(KeyCode::Delete, KeyModifiers::CONTROL),
+				EditCommand::KillWord,
+			),
+			(
+				KeyEvent::new(KeyCode::Delete, KeyModifiers::ALT),
+				EditCommand::KillWord,
+			),
+		];
+		for (key, command) in defaults {
+			keymap.bind(&[key], command);
+		}
+		#[cfg(feature = "emacs")]
+		{
+			let alt = |code| KeyEvent::new(code, KeyModifiers::ALT);
+			let emacs = [
+				(ctrl('a'), EditCommand::BeginningOfLine),
+				(ctrl('e'), EditCommand::EndOfLine),
+				(ctrl('b'), EditCommand::BackwardChar),
+				(ctrl('f'), EditCommand::ForwardChar),
+				(alt(KeyCode::Char('b')), EditCommand::BackwardWord),
+				(alt(KeyCode::Char('f')), EditCommand::ForwardWord),
+				(ctrl('k'), EditCommand::KillLine),
+				(ctrl('w'), EditCommand::UnixWordRubout),
+				(ctrl('y'), EditCommand::Yank),
+				(alt(KeyCode::Char('d')), EditCommand::KillWord),
+				(alt(KeyCode::Char('y')), EditCommand::YankPop),
+				// Ctrl-_ arrives as Ctrl-7
+				(ctrl('7'), EditCommand::Undo),
+				(ctrl('_'), EditCommand::Undo),
+				(ctrl('z'), EditCommand::Undo),
+				(alt(KeyCode::Char('_')), EditCommand::Redo),
+			];
+			for (key, command) in emacs {
+				keymap.bind(&[key], command);
+			}
+		}
+		keymap
+	}
+	/// Create a keymap without any bindings. Characters are still inserted.
+	pub fn empty() -> Self {
+		Self {
+			bindings: HashMap::new(),
+		}
+	}
+	/// Bind a key sequence (e.g. Ctrl-X Ctrl-E) to a command or callback, returning the binding it replaces
+	pub fn bind(&mut self, keys: &[KeyEvent], binding: impl Into<Binding>) -> Option<Binding> {
+		self.bindings.insert(normalize(keys), binding.into())
+	}
+	/// Remove the binding of a key sequence, returning it
+	pub fn unbind(&mut self, keys: &[KeyEvent]) -> Option<Binding> {
+		self.bindings.remove(&normalize(keys))
+	}
+	pub(crate) fn lookup(&self, keys: &[KeyEvent]) -> Lookup {
+		let keys = self.resolve(keys);
+		if self.is_prefix(&keys) {
+			Lookup::Prefix
+		} else if self.bindings.contains_key(&keys) {
+			Lookup::Bound
+		} else {
+			Lookup::Unbound
+		}
+	}
+	/// Binding of exactly this key sequence
+	pub(crate) fn get_mut(&mut self, keys: &[KeyEvent]) -> Option<&mut Binding> {
+		let keys = self.resolve(keys);
+		self.bindings.get_mut(&keys)
+	}
+	/// Whether the keys start a longer bound sequence
+	fn is_prefix(&self, keys: &[KeyEvent]) -> bool {
+		self.bindings
+			.keys()
+			.any(|bound| bound.len() > keys.len() && bound.starts_with(keys))
+	}
+	/// Key sequence to look up, falling back to the keys without Shift if only those are bound
+	fn resolve(&self, keys: &[KeyEvent]) -> Vec<KeyEvent> {
+		let keys = normalize(keys);
+		if self.bindings.contains_key(&keys) || self.is_prefix(&keys) {
+			return keys;
+		}
+		keys.iter()
+			.map(|key| KeyEvent::new(key.code, key.modifiers - KeyModifiers::SHIFT))
+			.collect()
+	}
+}
+impl Default for Keymap {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Drop Shift from characters, which are distinguished by case already, and from Shift-Tab
+fn normalize(keys: &[KeyEvent]) -> Vec<KeyEvent> {
+	keys.iter()
+		.map(|key| match key.code {
+			KeyCode::Char(_) | KeyCode::BackTab => {
+				KeyEvent::new(key.code, key.modifiers - KeyModifiers::SHIFT)
+			}
+			_ => *key,
+		})
+		.collect()
+}
@@ -1,23 +1,51 @@
 use std::{
-	io::{self, stdout, Stdout, Write},
+	fmt,
+	io::{self, Write},
 	ops::DerefMut,
+	path::{Path, PathBuf},
 	pin::Pin,
 	task::{Context, Poll},
+	time::Duration,
 };
 
 use crossterm::{
-	event::EventStream,
-	terminal::{self, disable_raw_mode},
-	QueueableCommand,
+	event::{Event, KeyCode, KeyEvent, KeyModifiers},
+	terminal,
+	tty::IsTty,
+	Command, ExecutableCommand, QueueableCommand,
 };
-use futures::{channel::mpsc, prelude::*};
+use futures::{channel::mpsc, prelude::*, stream::FusedStream};
 use thingbuf::mpsc::{errors::TrySendError, Receiver, Sender};
 use thiserror::Error;
 
+mod backend;
+mod completion;
+mod decode;
+mod highlight;
+mod hint;
 mod history;
+mod inputrc;
+mod keymap;
 mod line;
+mod live;
+mod plain;
+mod validate;
+pub use backend::{Backend, CrosstermBackend, InputEvent, MemoryBackend, StreamBackend};
+use completion::SyncCompleter;
+pub use completion::{AsyncCompleter, Completer, Completion};
+pub use highlight::Highlighter;
+pub use hint::Hinter;
 use history::History;
-use line::LineState;
+pub use inputrc::InputrcWarning;
+pub use keymap::{Binding, EditCommand, Keymap, LineBuffer, UnknownCommand};
+pub use line::StatusPosition;
+use line::{Ask, Confirm, KeyPrompt, LineState, Select};
+#[cfg(feature = "vi")]
+pub use line::{EditMode, ViMode};
+pub use live::LiveRegion;
+use live::LiveUpdates;
+use plain::{NoTerminal, PlainInput};
+pub use validate::{ValidationResult, Validator};
 
 /// Error returned from `readline()`
 #[derive(Debug, Error)]
@@ -58,9 +86,8 @@ impl AsyncWrite for SharedWriter {
 		if this.buffer.ends_with(b"\n") {
 			let fut = this.sender.send_ref();
 			futures::pin_mut!(fut);
-			let mut send_buf = futures::ready!(fut.poll_unpin(cx)).map_err(|_| {
-				io::Error::new(io::ErrorKind::Other, "thingbuf receiver has closed")
-			})?;
+			let mut send_buf = futures::ready!(fut.poll_unpin(cx))
+				.map_err(|_| io::Error::other("thingbuf receiver has closed"))?;
 			// Swap buffers
 			std::mem::swap(send_buf.deref_mut(), &mut this.buffer);
 			this.buffer.clear();
@@ -74,7 +101,7 @@ impl AsyncWrite for SharedWriter {
 		let fut = this.sender.send_ref();
 		futures::pin_mut!(fut);
 		let mut send_buf = futures::ready!(fut.poll_unpin(cx))
-			.map_err(|_| io::Error::new(io::ErrorKind::Other, "thingbuf receiver has closed"))?;
+			.map_err(|_| io::Error::other("thingbuf receiver has closed"))?;
 		// Swap buffers
 		std::mem::swap(send_buf.deref_mut(), &mut this.buffer);
 		this.buffer.clear();
@@ -95,10 +122,7 @@ impl io::Write for SharedWriter {
 				}
 				Err(TrySendError::Full(_)) => return Err(io::ErrorKind::WouldBlock.into()),
 				_ => {
-					return Err(io::Error::new(
-						io::ErrorKind::Other,
-						"thingbuf receiver has closed",
-					));
+					return Err(io::Error::other("thingbuf receiver has closed"));
 				}
 			}
 		}
@@ -109,35 +133,89 @@ impl io::Write for SharedWriter {
 	}
 }
 
+/// Clonable handle for changing the prompt of a [`Readline`] from other tasks
+#[derive(Clone)]
+pub struct PromptHandle {
+	sender: mpsc::UnboundedSender<String>,
+}
+impl PromptHandle {
+	/// Replace the prompt, the line being edited is redrawn with it the next time `readline()` is polled
+	pub fn set_prompt(&self, prompt: String) -> Option<()> {
+		self.sender.unbounded_send(prompt).ok()
+	}
+}
+
+/// Clonable handle for changing the status lines of a [`Readline`] from other tasks
+#[derive(Clone)]
+pub struct StatusHandle {
+	sender: mpsc::UnboundedSender<Option<String>>,
+}
+impl StatusHandle {
+	/// Replace the status lines, or remove them if `None`. They are redrawn the next time `readline()` is polled.
+	pub fn set_status(&self, status: Option<String>) -> Option<()> {
+		self.sender.unbounded_send(status).ok()
+	}
+}
+
 /// Structure that contains all the data necessary to read and write lines in an asyncronous manner
 pub struct Readline {
-	raw_term: Stdout,
-	event_stream: EventStream, // Stream of events
+	raw_term: Box<dyn Backend>,
 	line_receiver: Receiver<Vec<u8>>,
 
 	line: LineState, // Current line
 
-	history_sender: mpsc::UnboundedSender<String>,
+	prompt_sender: mpsc::UnboundedSender<String>,
+	prompt_receiver: mpsc::UnboundedReceiver<String>,
+
+	status_sender: mpsc::UnboundedSender<Option<String>>,
+	status_receiver: mpsc::UnboundedReceiver<Option<String>>,
+
+	live: LiveUpdates,
+
+	// Input read line by line when stdin is not a terminal
+	plain: Option<PlainInput>,
 }
 
 impl Readline {
-	/// Create new Readline
+	/// Create new Readline.
+	/// The prompt may contain ANSI escape sequences (e.g. colours), which don't count towards its width.
+	/// If stdin is not a terminal (e.g. input is piped in), lines are read from it as they are, without editing,
+	/// and the prompt is only printed if enabled with `set_echo`.
 	pub fn new(prompt: String) -> Result<(Self, SharedWriter), ReadlineError> {
+		if !io::stdin().is_tty() {
+			let (mut readline, writer) = Self::with_backend(prompt, NoTerminal)?;
+			readline.plain = Some(PlainInput::spawn());
+			return Ok((readline, writer));
+		}
+		Self::with_backend(prompt, CrosstermBackend::new())
+	}
+	/// Create new Readline that reads input from and renders to the given backend instead of the terminal
+	pub fn with_backend(
+		prompt: String,
+		backend: impl Backend + 'static,
+	) -> Result<(Self, SharedWriter), ReadlineError> {
 		let (sender, line_receiver) = thingbuf::mpsc::channel(500);
-		terminal::enable_raw_mode()?;
+		let mut raw_term: Box<dyn Backend> = Box::new(backend);
+		raw_term.enable_raw_mode()?;
 
-		let line = LineState::new(prompt, terminal::size()?);
-		let history_sender = line.history.sender.clone();
+		let line = LineState::new(prompt, raw_term.size()?);
+		let (prompt_sender, prompt_receiver) = mpsc::unbounded();
+		let (status_sender, status_receiver) = mpsc::unbounded();
 
 		let mut readline = Readline {
-			raw_term: stdout(),
-			event_stream: EventStream::new(),
+			raw_term,
 			line_receiver,
 			line,
-			history_sender,
+			prompt_sender,
+			prompt_receiver,
+			status_sender,
+			status_receiver,
+			live: LiveUpdates::new(),
+			plain: None,
 		};
 		readline.line.render(&mut readline.raw_term)?;
 		readline.raw_term.queue(terminal::EnableLineWrap)?;
+		readline.raw_term.queue(BracketedPaste(true))?;
 		readline.raw_term.flush()?;
 		Ok((
 			readline,
@@ -147,48 +225,474 @@ impl Readline {
 			},
 		))
 	}
+	/// Create new Readline for a remote terminal, reading raw input bytes from `reader` and writing output to `writer`,
+	/// e.g. the two halves of a socket. `size` is the initial number of columns and rows of the terminal
+	/// and `resizes` receives its new size whenever it changes.
+	/// The process's own terminal is left alone, and the output of synchronous methods like `set_prompt`
+	/// is only written out the next time `readline()` is polled.
+	pub fn with_stream(
+		prompt: String,
+		reader: impl AsyncRead + Unpin + Send + 'static,
+		writer: impl AsyncWrite + Unpin + Send + 'static,
+		size: (u16, u16),
+		resizes: impl Stream<Item = (u16, u16)> + Send + 'static,
+	) -> Result<(Self, SharedWriter), ReadlineError> {
+		Self::with_backend(prompt, StreamBackend::new(reader, writer, size, resizes))
+	}
+	/// Whether lines are edited in a terminal, as opposed to read as they are from non-interactive stdin
+	pub fn is_interactive(&self) -> bool {
+		self.plain.is_none()
+	}
+	/// Print the prompt followed by each line read when stdin is not a terminal, like a transcript of the input.
+	/// Off by default, and without effect in a terminal.
+	pub fn set_echo(&mut self, echo: bool) {
+		if let Some(plain) = &mut self.plain {
+			plain.echo = echo;
+		}
+	}
+	/// Replace the prompt, redrawing the line being edited in place
+	pub fn set_prompt(&mut self, prompt: String) -> io::Result<()> {
+		self.line.set_prompt(prompt, &mut self.raw_term)?;
+		self.raw_term.flush()
+	}
+	/// Revert the last edit of the line being edited, returning `false` if there is nothing to undo
+	pub fn undo(&mut self) -> io::Result<bool> {
+		let undone = self.line.undo(&mut self.raw_term)?;
+		self.raw_term.flush()?;
+		Ok(undone)
+	}
+	/// Reapply the last undone edit, returning `false` if there is nothing to redo
+	pub fn redo(&mut self) -> io::Result<bool> {
+		let redone = self.line.redo(&mut self.raw_term)?;
+		self.raw_term.flush()?;
+		Ok(redone)
+	}
+	/// Switch between emacs and vi key bindings. Vi mode starts out in insert mode.
+	#[cfg(feature = "vi")]
+	pub fn set_edit_mode(&mut self, mode: EditMode) -> io::Result<()> {
+		self.line.set_edit_mode(mode, &mut self.raw_term)?;
+		self.raw_term.flush()
+	}
+	/// Show the text returned by `indicator` in front of the prompt while in vi mode,
+	/// e.g. `[I]` in insert mode and `[N]` in normal mode
+	#[cfg(feature = "vi")]
+	pub fn set_vi_mode_indicator(
+		&mut self,
+		indicator: impl Fn(ViMode) -> String + Send + 'static,
+	) -> io::Result<()> {
+		self.line
+			.set_vi_mode_indicator(Box::new(indicator), &mut self.raw_term)?;
+		self.raw_term.flush()
+	}
+	/// Get a clonable handle for changing the prompt from other tasks
+	pub fn prompt_handle(&self) -> PromptHandle {
+		PromptHandle {
+			sender: self.prompt_sender.clone(),
+		}
+	}
+	/// Show status lines (e.g. connection state or progress) above or below the line being edited,
+	/// or remove them if `None`. They are redrawn along with the line and never scroll away with `SharedWriter` output.
+	/// Lines wider than the terminal are truncated.
+	pub fn set_status(&mut self, status: Option<String>) -> io::Result<()> {
+		self.line.set_status(status, &mut self.raw_term)?;
+		self.raw_term.flush()
+	}
+	/// Show the status lines above or below the line being edited, below by default
+	pub fn set_status_position(&mut self, position: StatusPosition) -> io::Result<()> {
+		self.line
+			.set_status_position(position, &mut self.raw_term)?;
+		self.raw_term.flush()
+	}
+	/// Get a clonable handle for changing the status lines from other tasks
+	pub fn status_handle(&self) -> StatusHandle {
+		StatusHandle {
+			sender: self.status_sender.clone(),
+		}
+	}
+	/// Add lines that are updated in place between the output and the prompt (e.g. a progress bar or spinner),
+	/// below any other live regions, and return the handle for updating and finishing them
+	pub fn add_live_region(&mut self, text: impl Into<String>) -> io::Result<LiveRegion> {
+		let (id, region) = self.live.region();
+		self.line
+			.add_live_region(id, text.into(), &mut self.raw_term)?;
+		self.raw_term.flush()?;
+		Ok(region)
+	}
+	/// Set the minimum time between redraws of live regions, 100ms by default.
+	/// Updates in between are combined, only finishing a region is drawn right away.
+	pub fn set_live_region_interval(&mut self, interval: Duration) {
+		self.live.interval = interval;
+	}
+	/// Bind a key sequence (e.g. Ctrl-X Ctrl-E) to an editing command or to a callback created with `Binding::callback`,
+	/// returning the binding it replaces
+	pub fn bind(&mut self, keys: &[KeyEvent], binding: impl Into<Binding>) -> Option<Binding> {
+		self.line.keymap.bind(keys, binding)
+	}
+	/// Remove the binding of a key sequence, returning it
+	pub fn unbind(&mut self, keys: &[KeyEvent]) -> Option<Binding> {
+		self.line.keymap.unbind(keys)
+	}
+	/// Replace all key bindings
+	pub fn set_keymap(&mut self, keymap: Keymap) {
+		self.line.keymap = keymap;
+	}
+	/// Apply the key bindings and settings of a GNU readline inputrc file (usually `~/.inputrc`).
+	/// Supports `set editing-mode`, `set completion-ignore-case`, key bindings to command names and `$if` blocks,
+	/// which match `application`, `mode=` or `term=`.
+	/// Unsupported lines are skipped and returned as warnings.
+	pub fn load_inputrc(
+		&mut self,
+		path: impl AsRef<Path>,
+		application: &str,
+	) -> io::Result<Vec<InputrcWarning>> {
+		let contents = std::fs::read_to_string(path)?;
+		self.apply_inputrc(&contents, application)
+	}
+	/// Apply key bindings and settings in the inputrc format, see [`Readline::load_inputrc`]
+	pub fn apply_inputrc(
+		&mut self,
+		contents: &str,
+		application: &str,
+	) -> io::Result<Vec<InputrcWarning>> {
+		inputrc::apply(contents, application, &mut self.line, &mut self.raw_term)
+	}
+	/// Whether the common prefix of completion candidates is found ignoring case
+	pub fn set_completion_ignore_case(&mut self, ignore_case: bool) {
+		self.line.completion_ignore_case = ignore_case;
+	}
 	/// Set max history length
 	pub fn set_max_history(&mut self, max_size: usize) {
 		self.line.history.max_size = max_size;
 		self.line.history.entries.truncate(max_size);
 	}
+	/// Set the source of tab completions.
+	/// Tab inserts the longest common prefix of the candidates, a second Tab lists them and further presses cycle through them.
+	pub fn set_completer(&mut self, completer: impl Completer + 'static) {
+		self.line.completer = Some(Box::new(SyncCompleter(completer)));
+	}
+	/// Set a source of tab completions that resolves asynchronously.
+	/// Keys and `SharedWriter` output keep being processed while the completions resolve,
+	/// and they are discarded if the line was edited in the meantime.
+	pub fn set_async_completer(&mut self, completer: impl AsyncCompleter + 'static) {
+		self.line.completer = Some(Box::new(completer));
+	}
+	/// Set the validator deciding whether Enter submits the line or inserts a newline to continue editing on the next line
+	pub fn set_validator(&mut self, validator: impl Validator + 'static) {
+		self.line.validator = Some(Box::new(validator));
+	}
+	/// Set the prompt displayed in front of every line of multi-line input but the first
+	pub fn set_continuation_prompt(&mut self, prompt: String) -> io::Result<()> {
+		self.line
+			.set_continuation_prompt(prompt, &mut self.raw_term)?;
+		self.raw_term.flush()
+	}
+	/// Set the highlighter used to style the line as it is edited
+	pub fn set_highlighter(&mut self, highlighter: impl Highlighter + 'static) -> io::Result<()> {
+		self.line.highlighter = Some(Box::new(highlighter));
+		self.line.clear_and_render(&mut self.raw_term)?;
+		self.raw_term.flush()
+	}
+	/// Set the source of the suggestion shown dimmed after the line, replacing the suggestions from history
+	pub fn set_hinter(&mut self, hinter: impl Hinter + 'static) -> io::Result<()> {
+		self.line.hinter = Some(Box::new(hinter));
+		self.line.clear_and_render(&mut self.raw_term)?;
+		self.raw_term.flush()
+	}
+	/// Whether the most recent history entry starting with the line is suggested after it when no hinter is set.
+	/// On by default.
+	pub fn set_history_hints(&mut self, enabled: bool) -> io::Result<()> {
+		self.line.history_hints = enabled;
+		self.line.clear_and_render(&mut self.raw_term)?;
+		self.raw_term.flush()
+	}
 	/// Flush terminal
 	pub fn flush(&mut self) -> io::Result<()> {
 		self.raw_term.flush()
 	}
 	/// Polling function for readline, manages all input and output.
 	pub async fn readline(&mut self) -> Result<String, ReadlineError> {
+		self.restore_line().await?;
+		self.read_line().await
+	}
+	/// Read a password or other secret with `prompt`, displaying `mask` (e.g. `*`) for every character typed or nothing if `None`.
+	/// The password is never suggested from or added to the history, and completions, hints, highlighting and validation are skipped.
+	/// The line being edited is set aside meanwhile and restored afterwards, and the password's copy of the line is zeroized.
+	/// Keys bound to callbacks do nothing meanwhile, as callbacks would be handed the password.
+	pub async fn read_password(
+		&mut self,
+		prompt: String,
+		mask: Option<char>,
+	) -> Result<String, ReadlineError> {
+		self.restore_line().await?;
+		self.line.start_password(prompt, mask, &mut self.raw_term)?;
+		flush_backend(&mut self.raw_term).await?;
+		let result = self.read_line().await;
+		self.line.end_password(&mut self.raw_term)?;
+		flush_backend(&mut self.raw_term).await?;
+		result
+	}
+	/// Ask a yes or no question, answered with y or n, or with Enter if there is a `default`.
+	/// The choices are shown after the prompt as `[y/n]`, with the default in upper case.
+	/// The line being edited is hidden meanwhile and restored afterwards, and Esc or Ctrl-C return `Err(Interrupted)`.
+	/// When stdin is not a terminal, it is answered by a line of input with y, yes, n or no, or an empty one for the default.
+	pub async fn confirm(
+		&mut self,
+		prompt: String,
+		default: Option<bool>,
+	) -> Result<bool, ReadlineError> {
+		self.ask(Confirm { prompt, default }).await
+	}
+	/// Let the user pick one of `options`, listed below the prompt, with the arrow keys (or Tab, Ctrl-N/P and j/k) and Enter,
+	/// returning its index. Lists longer than the terminal scroll.
+	/// The line being edited is hidden meanwhile and restored afterwards, and Esc or Ctrl-C return `Err(Interrupted)`.
+	/// When stdin is not a terminal, an option is picked by a line of input with its text or number, starting at 1.
+	pub async fn select(
+		&mut self,
+		prompt: String,
+		options: Vec<String>,
+	) -> Result<usize, ReadlineError> {
+		if options.is_empty() {
+			return Err(
+				io::Error::new(io::ErrorKind::InvalidInput, "no options to select from").into(),
+			);
+		}
+		let widget = Select {
+			prompt,
+			options,
+			selected: 0,
+			scroll: 0,
+		};
+		self.ask(widget).await
+	}
+	/// Show the prompt until a key is pressed, e.g. for "Press any key to continue", and return the key.
+	/// The line being edited is hidden meanwhile and restored afterwards, and Ctrl-C returns `Err(Interrupted)`.
+	/// When stdin is not a terminal, a line of input is read instead and its first character returned, or Enter if it is empty.
+	pub async fn read_key(&mut self, prompt: String) -> Result<KeyEvent, ReadlineError> {
+		self.ask(KeyPrompt { prompt }).await
+	}
+	/// Show a widget in place of the prompt and line until it is answered
+	async fn ask<W: Ask>(&mut self, widget: W) -> Result<W::Answer, ReadlineError> {
+		self.restore_line().await?;
+		self.line.start_widget(widget.into(), &mut self.raw_term)?;
+		flush_backend(&mut self.raw_term).await?;
+		let answer = match self.plain {
+			Some(_) => loop {
+				let line = match self.read_plain_line().await {
+					Ok(line) => line,
+					Err(e) => break Err(e),
+				};
+				if let Some(answer) = self.line.widget_answer::<W>(&line) {
+					break Ok(answer);
+				}
+			},
+			None => {
+				self.read_events(|line, event, term| line.handle_widget_event::<W>(event, term))
+					.await
+			}
+		};
+		self.line.end_widget(&mut self.raw_term)?;
+		flush_backend(&mut self.raw_term).await?;
+		answer
+	}
+	/// Restore the line if reading a password or showing a widget was cancelled
+	async fn restore_line(&mut self) -> io::Result<()> {
+		self.line.end_password(&mut self.raw_term)?;
+		self.line.end_widget(&mut self.raw_term)?;
+		flush_backend(&mut self.raw_term).await
+	}
+	async fn read_line(&mut self) -> Result<String, ReadlineError> {
+		if self.plain.is_some() {
+			return self.read_plain_line().await;
+		}
+		self.read_events(|line, event, term| line.handle_event(event, term))
+			.await
+	}
+	/// Handle input events with `handle` until it returns a result, managing all other output meanwhile
+	async fn read_events<T>(
+		&mut self,
+		mut handle: impl FnMut(
+			&mut LineState,
+			Event,
+			&mut Box<dyn Backend>,
+		) -> Result<Option<T>, ReadlineError>,
+	) -> Result<T, ReadlineError> {
 		loop {
 			futures::select! {
-				event = self.event_stream.next().fuse() => match event {
-					Some(Ok(event)) => {
-						match self.line.handle_event(event, &mut self.raw_term).await {
-							Ok(Some(line)) => return Result::<_, ReadlineError>::Ok(line),
+				input = future::poll_fn(|cx| self.raw_term.poll_event(cx)).fuse() => match input {
+					// Characters are commands rather than text here, so handle them like typed keys
+					Some(Ok(InputEvent::Paste(text))) if self.line.types_pastes() => {
+						for c in text.chars() {
+							let code = match c {
+								'\r' | '\n' => KeyCode::Enter,
+								'\t' => KeyCode::Tab,
+								c => KeyCode::Char(c),
+							};
+							let key = KeyEvent::new(code, KeyModifiers::NONE);
+							if let Some(result) = handle(&mut self.line, Event::Key(key), &mut self.raw_term)? {
+								self.line.forget_input(text);
+								flush_backend(&mut self.raw_term).await?;
+								return Ok(result);
+							}
+						}
+						self.line.forget_input(text);
+						flush_backend(&mut self.raw_term).await?;
+					}
+					Some(Ok(InputEvent::Paste(text))) => {
+						self.line.paste(&text, &mut self.raw_term)?;
+						self.line.forget_input(text);
+						flush_backend(&mut self.raw_term).await?;
+					}
+					Some(Ok(InputEvent::Event(event))) => {
+						match handle(&mut self.line, event, &mut self.raw_term) {
+							Ok(Some(result)) => {
+								flush_backend(&mut self.raw_term).await?;
+								return Result::<_, ReadlineError>::Ok(result);
+							}
 							Err(e) => return Err(e),
-							Ok(None) => self.raw_term.flush()?,
+							Ok(None) => {}
 						}
+						flush_backend(&mut self.raw_term).await?;
 					}
 					Some(Err(e)) => return Err(e.into()),
-					None => {},
+					// The input has ended
+					None => return Err(ReadlineError::Eof),
 				},
 				result = self.line_receiver.recv_ref().fuse() => match result {
 					Some(buf) => {
 						self.line.print_data(&buf, &mut self.raw_term)?;
-						self.raw_term.flush()?;
+						flush_backend(&mut self.raw_term).await?;
 					},
 					None => return Err(ReadlineError::Closed),
+				},
+				prompt = self.prompt_receiver.next() => if let Some(prompt) = prompt {
+					self.line.set_prompt(prompt, &mut self.raw_term)?;
+					flush_backend(&mut self.raw_term).await?;
+				},
+				status = self.status_receiver.next() => if let Some(status) = status {
+					self.line.set_status(status, &mut self.raw_term)?;
+					flush_backend(&mut self.raw_term).await?;
+				},
+				updates = future::poll_fn(|cx| self.live.poll_due(cx)).fuse() => {
+					let finished = self.line.update_live_regions(updates, &mut self.raw_term)?;
+					if !finished.is_empty() {
+						self.line.print_data(finished.as_bytes(), &mut self.raw_term)?;
+					}
+					flush_backend(&mut self.raw_term).await?;
+				},
+				candidates = future::poll_fn(|cx| self.line.poll_pending_completion(cx)).fuse() => {
+					self.line.finish_pending_completion(candidates, &mut self.raw_term)?;
+					flush_backend(&mut self.raw_term).await?;
 				}
 			}
 		}
 	}
-	/// Add history entry asyncronously
+	/// Read the next line of non-interactive stdin, printing `SharedWriter` output as is in the meantime
+	async fn read_plain_line(&mut self) -> Result<String, ReadlineError> {
+		let mut stdout = io::stdout();
+		let plain = match &mut self.plain {
+			// Reading again after the input ended
+			Some(plain) if plain.lines.is_terminated() => {
+				print_remaining(&self.line_receiver, &mut stdout)?;
+				return Err(ReadlineError::Eof);
+			}
+			Some(plain) => plain,
+			None => return Err(ReadlineError::Closed),
+		};
+		loop {
+			futures::select! {
+				line = plain.lines.next() => match line {
+					Some(Ok(line)) => {
+						if plain.echo {
+							writeln!(stdout, "{}{}", self.line.plain_prompt(), self.line.mask(&line))?;
+							stdout.flush()?;
+						}
+						return Ok(line);
+					}
+					Some(Err(e)) => return Err(e.into()),
+					None => {
+						print_remaining(&self.line_receiver, &mut stdout)?;
+						return Err(ReadlineError::Eof);
+					}
+				},
+				result = self.line_receiver.recv_ref().fuse() => match result {
+					Some(buf) => {
+						stdout.write_all(&buf)?;
+						stdout.flush()?;
+					}
+					None => return Err(ReadlineError::Closed),
+				},
+				prompt = self.prompt_receiver.next() => if let Some(prompt) = prompt {
+					self.line.set_prompt(prompt, &mut self.raw_term)?;
+				},
+				status = self.status_receiver.next() => if let Some(status) = status {
+					self.line.set_status(status, &mut self.raw_term)?;
+				},
+				updates = future::poll_fn(|cx| self.live.poll_due(cx)).fuse() => {
+					let finished = self.line.update_live_regions(updates, &mut self.raw_term)?;
+					stdout.write_all(finished.as_bytes())?;
+					stdout.flush()?;
+				},
+			}
+		}
+	}
+	/// Add history entry, appending it to the file set with `set_history_append_file` if any.
+	/// Returns `None` if writing it to the file fails, see `try_add_history_entry` for the error.
 	pub fn add_history_entry(&mut self, entry: String) -> Option<()> {
-		self.history_sender.unbounded_send(entry).ok()
+		self.try_add_history_entry(entry).ok()
+	}
+	/// Add history entry, appending it to the file set with `set_history_append_file` if any.
+	/// The entry is added even if writing it to the file fails, the error is returned afterwards.
+	pub fn try_add_history_entry(&mut self, entry: String) -> io::Result<()> {
+		self.line.history.add(entry)
+	}
+	/// Load history entries from a file written by `save_history` or in append mode.
+	/// Loaded entries are treated as older than any entries added afterwards.
+	pub fn load_history(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+		self.line.history.load(path.as_ref())
+	}
+	/// Save the current history to a file (oldest entry first, one entry per line), replacing its contents.
+	/// Newlines and backslashes within entries are escaped so that multi-line entries round-trip.
+	pub fn save_history(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+		self.line.history.save(path.as_ref())
+	}
+	/// Append every entry added from now on to the given file, or stop appending if `None`.
+	/// The file is truncated to the newest entries whenever it grows past the maximum history size.
+	pub fn set_history_append_file(&mut self, path: Option<PathBuf>) -> io::Result<()> {
+		self.line.history.set_append_file(path)
 	}
 }
 
 impl Drop for Readline {
 	fn drop(&mut self) {
-		let _ = disable_raw_mode();
+		let _ = self.raw_term.execute(BracketedPaste(false));
+		// Output written asynchronously gets a single chance to go out, as dropping can't wait for it
+		let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+		let _ = self.raw_term.poll_flush(&mut cx);
+		let _ = self.raw_term.disable_raw_mode();
+	}
+}
+
+/// Write out the output buffered by the backend
+async fn flush_backend(raw_term: &mut Box<dyn Backend>) -> io::Result<()> {
+	future::poll_fn(|cx| raw_term.poll_flush(cx)).await
+}
+
+/// Print what was written before the input ended
+fn print_remaining(line_receiver: &Receiver<Vec<u8>>, stdout: &mut io::Stdout) -> io::Result<()> {
+	while let Ok(buf) = line_receiver.try_recv_ref() {
+		stdout.write_all(&buf)?;
+	}
+	stdout.flush()
+}
+
+/// Enables or disables bracketed paste mode, in which terminals mark pasted text
+struct BracketedPaste(bool);
+impl Command for BracketedPaste {
+	fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+		f.write_str(if self.0 { "\x1b[?2004h" } else { "\x1b[?2004l" })
+	}
+	#[cfg(windows)]
+	fn execute_winapi(&self) -> crossterm::Result<()> {
+		Ok(())
 	}
 }
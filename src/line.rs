@@ -1,16 +1,83 @@
-use std::io::{self, Write};
+use std::{
+	borrow::Cow,
+	io::{self, Write},
+	ops::Range,
+};
 
 use crossterm::{
 	cursor,
 	event::{Event, KeyCode, KeyEvent, KeyModifiers},
+	style::Stylize,
 	terminal::{Clear, ClearType::*},
-	QueueableCommand,
+	Command, QueueableCommand,
 };
 
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
-use crate::{History, ReadlineError};
+use crate::{
+	highlight::write_highlighted,
+	keymap::{Binding, Lookup},
+	AsyncCompleter, EditCommand, Highlighter, Hinter, History, Keymap, LineBuffer, ReadlineError,
+	ValidationResult, Validator,
+};
+
+mod completion;
+mod hint;
+mod kill_ring;
+mod live;
+mod password;
+mod search;
+mod status;
+mod undo;
+#[cfg(feature = "vi")]
+mod vi;
+mod widget;
+use completion::{CompletionState, PendingCompletion};
+use kill_ring::{KillRing, LastCommand};
+use password::Password;
+use search::Search;
+pub use status::StatusPosition;
+use undo::UndoStack;
+#[cfg(feature = "vi")]
+use vi::ViState;
+#[cfg(feature = "vi")]
+pub use vi::{EditMode, ViMode};
+pub use widget::{Ask, Confirm, KeyPrompt, Select, Widget};
+
+/// Number of columns taken up by `text` on the terminal, skipping ANSI escape sequences
+pub(crate) fn display_width(text: &str) -> usize {
+	let mut width = 0;
+	let mut rest = text;
+	while let Some(start) = rest.find('\x1b') {
+		width += UnicodeWidthStr::width(&rest[..start]);
+		let mut chars = rest[start + 1..].char_indices();
+		let end = match chars.next() {
+			// Control sequence, ends with a byte in the range `@` to `~`
+			Some((_, '[')) => chars
+				.find(|(_, c)| ('@'..='~').contains(c))
+				.map(|(i, c)| i + c.len_utf8()),
+			// Operating system command, ends with BEL or ST (`ESC \`)
+			Some((_, ']')) => {
+				let osc = &rest[start + 2..];
+				match (osc.find('\x07'), osc.find("\x1b\\")) {
+					(Some(bel), Some(st)) if st < bel => Some(1 + st + 2),
+					(Some(bel), _) => Some(1 + bel + 1),
+					(None, Some(st)) => Some(1 + st + 2),
+					(None, None) => None,
+				}
+			}
+			// Two character escape sequence
+			Some((i, c)) => Some(i + c.len_utf8()),
+			None => None,
+		};
+		rest = match end {
+			Some(end) => &rest[start + 1 + end..],
+			None => "",
+		};
+	}
+	width + UnicodeWidthStr::width(rest)
+}
 
 #[derive(Default)]
 pub struct LineState {
@@ -18,95 +85,467 @@ pub struct LineState {
 	line: String,
 	// Index of grapheme in line
 	line_cursor_grapheme: usize,
-	// Column of grapheme in line
+	// Row and column of the cursor, relative to the start of the prompt
+	current_row: u16,
 	current_column: u16,
 
 	cluster_buffer: String, // buffer for holding partial grapheme clusters as they come in
 
 	prompt: String,
+	// Prompt displayed in front of every line but the first
+	pub continuation_prompt: String,
 	last_line_length: usize,
 	last_line_completed: bool,
 
 	term_size: (u16, u16),
 
 	pub history: History,
+	// Incremental history search in progress, if any
+	search: Option<Search>,
+	last_search_query: String,
+
+	pub completer: Option<Box<dyn AsyncCompleter>>,
+	// Candidates of the Tab presses in progress, if any
+	completion: Option<CompletionState>,
+	pending_completion: Option<PendingCompletion>,
+	// Whether the common prefix of candidates is found ignoring case
+	pub completion_ignore_case: bool,
+
+	// Lines updated in place between the output and the prompt, in order of creation
+	live_regions: Vec<(u64, String)>,
+	// Lines shown above or below the line being edited, kept out of the scrollback
+	status: Vec<String>,
+	status_position: StatusPosition,
+	// Password being read in place of the line, if any
+	password: Option<Password>,
+	// Widget shown in place of the prompt and line, if any
+	widget: Option<Widget>,
+
+	pub highlighter: Option<Box<dyn Highlighter>>,
+	pub hinter: Option<Box<dyn Hinter>>,
+	// Whether the history suggests how to continue the line when there is no hinter
+	pub history_hints: bool,
+	pub validator: Option<Box<dyn Validator>>,
+
+	kill_ring: KillRing,
+	undo_stack: UndoStack,
+	// State of vi mode, if it is used instead of the emacs bindings
+	#[cfg(feature = "vi")]
+	vi: Option<ViState>,
+	// Text displayed in front of the prompt for each vi mode
+	#[cfg(feature = "vi")]
+	vi_mode_indicator: Option<Box<dyn Fn(ViMode) -> String + Send>>,
+	pub keymap: Keymap,
+	// Keys typed so far of a bound key sequence
+	pending_keys: Vec<KeyEvent>,
+	// Commands run by the current and previous event
+	last_command: LastCommand,
+	previous_command: LastCommand,
+}
+
+/// Whether a grapheme belongs to a whitespace delimited word
+fn is_word(grapheme: &str) -> bool {
+	!grapheme.chars().all(char::is_whitespace)
+}
+/// Whether a grapheme belongs to an alphanumeric word
+fn is_alphanumeric(grapheme: &str) -> bool {
+	grapheme.chars().any(char::is_alphanumeric)
+}
+
+/// Queue a relative cursor movement, skipping it when moving by 0 as terminals would move by 1 instead
+fn queue_move<C: Command>(
+	term: &mut impl Write,
+	count: u16,
+	movement: fn(u16) -> C,
+) -> io::Result<()> {
+	if count != 0 {
+		term.queue(movement(count))?;
+	}
+	Ok(())
+}
+
+/// Writer that starts every new line of the written text with the continuation prompt
+struct ContinuationWriter<'a, W: Write> {
+	term: &'a mut W,
+	prompt: &'a str,
+}
+impl<W: Write> Write for ContinuationWriter<'_, W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		for (i, part) in buf.split(|b| *b == b'\n').enumerate() {
+			if i > 0 {
+				write!(self.term, "\r\n{}", self.prompt)?;
+			}
+			self.term.write_all(part)?;
+		}
+		Ok(buf.len())
+	}
+	fn flush(&mut self) -> io::Result<()> {
+		self.term.flush()
+	}
 }
 
 impl LineState {
 	pub fn new(prompt: String, term_size: (u16, u16)) -> Self {
-		let current_column = prompt.len() as u16;
-		Self {
+		let mut state = Self {
 			prompt,
 			last_line_completed: true,
 			term_size,
-			current_column,
+			history_hints: true,
 
 			..Default::default()
-		}
-	}
-	fn line_height(&self, pos: u16) -> u16 {
-		pos / self.term_size.0 // Gets the number of lines wrapped
+		};
+		(state.current_row, state.current_column) = state.cursor_position(0);
+		state
 	}
 	/// Move from a position on the line to the start
-	fn move_to_beginning(&self, term: &mut impl Write, from: u16) -> io::Result<()> {
-		let move_up = self.line_height(from.saturating_sub(1));
-		term.queue(cursor::MoveToColumn(1))?
-			.queue(cursor::MoveUp(move_up))?;
-		Ok(())
+	fn move_to_beginning(&self, term: &mut impl Write, from_row: u16) -> io::Result<()> {
+		term.queue(cursor::MoveToColumn(0))?;
+		queue_move(term, from_row, cursor::MoveUp)
 	}
 	/// Move from the start of the line to some position
-	fn move_from_beginning(&self, term: &mut impl Write, to: u16) -> io::Result<()> {
-		let line_height = self.line_height(to.saturating_sub(1));
-		let line_remaining_len = to % self.term_size.0; // Get the remaining length
-		term.queue(cursor::MoveDown(line_height))?
-			.queue(cursor::MoveRight(line_remaining_len))?;
-		Ok(())
+	fn move_from_beginning(&self, term: &mut impl Write, row: u16, column: u16) -> io::Result<()> {
+		queue_move(term, row, cursor::MoveDown)?;
+		queue_move(term, column, cursor::MoveRight)
+	}
+	/// Row and column of the text at byte `index` of the line, relative to the start of the prompt.
+	/// The column equals the terminal width if the text fills its row exactly and the terminal has yet to wrap.
+	fn layout_position(&self, index: usize) -> (usize, usize) {
+		let width = self.term_size.0.max(1) as usize;
+		let start = (self.prompt_width() / width, self.prompt_width() % width);
+		self.advance(start, &self.mask(&self.line[..index]))
+	}
+	/// Row and column after writing `text` at the given row and column, wrapping like the terminal
+	fn advance(&self, (mut row, mut column): (usize, usize), text: &str) -> (usize, usize) {
+		let width = self.term_size.0.max(1) as usize;
+		let wrap = |column: usize| (column / width, column % width);
+		for grapheme in text.graphemes(true) {
+			if grapheme == "\n" {
+				let (prompt_rows, prompt_column) = wrap(display_width(&self.continuation_prompt));
+				row += 1 + prompt_rows;
+				column = prompt_column;
+			} else if grapheme == "\t" {
+				// Tabs move to the next tab stop, every 8 columns, without wrapping
+				column = ((column / 8 + 1) * 8).min(width - 1).max(column);
+			} else {
+				let grapheme_width = UnicodeWidthStr::width(grapheme);
+				if column + grapheme_width > width {
+					row += 1;
+					column = 0;
+				}
+				column += grapheme_width;
+			}
+		}
+		(row, column)
+	}
+	/// Row and column at which the cursor is displayed when at byte `index` of the line
+	fn cursor_position(&self, index: usize) -> (u16, u16) {
+		let (row, column) = self.layout_position(index);
+		let width = self.term_size.0 as usize;
+		// A wide grapheme that doesn't fit on the rest of the row is displayed at the start of the next one
+		let rest = self.mask(&self.line[index..]);
+		let wraps = rest
+			.graphemes(true)
+			.next()
+			.is_some_and(|g| g != "\n" && column + UnicodeWidthStr::width(g) > width);
+		if column < width && !wraps {
+			(row as u16, column as u16)
+		} else if rest.starts_with('\n') {
+			// Stay at the end of the filled row rather than on the next line's prompt
+			(row as u16, column as u16 - 1)
+		} else {
+			(row as u16 + 1, 0)
+		}
 	}
 	fn move_cursor(&mut self, change: isize) -> io::Result<()> {
 		// self.reset_cursor(term)?;
 		if change > 0 {
 			let count = self.line.graphemes(true).count();
 			self.line_cursor_grapheme =
-				usize::min(self.line_cursor_grapheme as usize + change as usize, count);
+				usize::min(self.line_cursor_grapheme + change as usize, count);
 		} else {
 			self.line_cursor_grapheme =
 				self.line_cursor_grapheme.saturating_sub((-change) as usize);
 		}
 		let (pos, str) = self.current_grapheme().unwrap_or((0, ""));
 		let pos = pos + str.len();
-		self.current_column =
-			(self.prompt.len() + UnicodeWidthStr::width(&self.line[0..pos])) as u16;
-
-		// self.set_cursor(term)?;
+		(self.current_row, self.current_column) = match &self.widget {
+			Some(widget) => self.widget_cursor(widget),
+			None => self.cursor_position(pos),
+		};
 
 		Ok(())
 	}
+	/// Whether pasted text is handled as typed keys instead, as characters are commands rather than text
+	/// while a widget is shown or in vi normal mode
+	pub fn types_pastes(&self) -> bool {
+		#[cfg(feature = "vi")]
+		if matches!(&self.vi, Some(vi) if vi.mode() == ViMode::Normal) {
+			return true;
+		}
+		self.widget.is_some()
+	}
+	/// Insert pasted text as a single edit, rendering only once.
+	/// Newlines are kept if a validator allows multi-line input and replaced by spaces otherwise.
+	pub fn paste(&mut self, text: &str, term: &mut impl Write) -> io::Result<()> {
+		let text = text.trim_end_matches(&['\r', '\n'][..]);
+		// The line is hidden behind the widget
+		if self.widget.is_some() {
+			return Ok(());
+		}
+		let newline = match (&self.validator, &self.password, &self.search) {
+			(Some(_), None, None) => '\n',
+			_ => ' ',
+		};
+		// Normalize line endings in a single buffer, so that no other copy of a pasted password is left to zeroize
+		let mut pasted = String::with_capacity(text.len());
+		let mut chars = text.chars().peekable();
+		while let Some(c) = chars.next() {
+			match c {
+				'\r' if chars.peek() == Some(&'\n') => {}
+				'\r' | '\n' => pasted.push(newline),
+				c => pasted.push(c),
+			}
+		}
+		if self.search.is_some() {
+			return self.extend_search(&pasted, term);
+		}
+		self.completion = None;
+		self.last_command = LastCommand::Other;
+		// Passwords are never copied for undo
+		let before = match self.password {
+			Some(_) => None,
+			None => Some((self.line.clone(), self.line_cursor_grapheme)),
+		};
+		self.clear(term)?;
+		let (pos, str) = self.current_grapheme().unwrap_or((0, ""));
+		let pos = pos + str.len();
+		self.reserve_password(pasted.len());
+		self.line.insert_str(pos, &pasted);
+		self.history.reset_position();
+		let new_cursor = self.line[..pos + pasted.len()].graphemes(true).count();
+		self.move_cursor(new_cursor as isize - self.line_cursor_grapheme as isize)?;
+		if let Some((line, cursor)) = before {
+			self.record_edit(line, cursor);
+		}
+		self.forget_input(pasted);
+		self.render(term)
+	}
+	/// Replace the continuation prompt, redrawing the line in place
+	pub fn set_continuation_prompt(
+		&mut self,
+		prompt: String,
+		term: &mut impl Write,
+	) -> io::Result<()> {
+		self.clear(term)?;
+		self.continuation_prompt = prompt;
+		self.move_cursor(0)?;
+		self.render(term)
+	}
+	/// Replace the prompt, redrawing the line in place
+	pub fn set_prompt(&mut self, prompt: String, term: &mut impl Write) -> io::Result<()> {
+		self.clear(term)?;
+		self.prompt = prompt;
+		self.move_cursor(0)?;
+		self.render(term)
+	}
+	/// Prompt currently displayed in front of the line
+	fn displayed_prompt(&self) -> Cow<'_, str> {
+		if let Some(search) = &self.search {
+			return search.prompt().into();
+		}
+		#[cfg(feature = "vi")]
+		if let (Some(vi), Some(indicator)) = (&self.vi, &self.vi_mode_indicator) {
+			return format!("{}{}", indicator(vi.mode()), self.prompt).into();
+		}
+		self.prompt.as_str().into()
+	}
+	/// Width of the prompt currently displayed in front of the line
+	fn prompt_width(&self) -> usize {
+		display_width(&self.displayed_prompt())
+	}
 	fn current_grapheme(&self) -> Option<(usize, &str)> {
 		self.line
 			.grapheme_indices(true)
 			.take(self.line_cursor_grapheme)
 			.last()
 	}
+	/// Replace a range of graphemes in the line and move the cursor to the end of the inserted text
+	fn replace_graphemes(&mut self, range: Range<usize>, text: &str) -> io::Result<()> {
+		let byte_index = |index: usize| {
+			self.line
+				.grapheme_indices(true)
+				.nth(index)
+				.map(|(pos, _)| pos)
+				.unwrap_or(self.line.len())
+		};
+		let (start, end) = (byte_index(range.start), byte_index(range.end));
+		self.reserve_password(text.len());
+		self.line.replace_range(start..end, text);
+		self.history.reset_position();
+		let cursor = range.start + text.graphemes(true).count();
+		self.move_cursor(-100000)?;
+		self.move_cursor(cursor as isize)
+	}
+	/// Move the cursor without changing the line, redrawing it if the highlighting depends on the cursor
+	/// or if a hint is shown or hidden as the cursor moves to or away from the end of the line
+	fn update_cursor(&mut self, change: isize, term: &mut impl Write) -> io::Result<()> {
+		let hinted = self.hint().is_some();
+		self.reset_cursor(term)?;
+		self.move_cursor(change)?;
+		if hinted
+			|| self.hint().is_some()
+			|| matches!(&self.highlighter, Some(highlighter) if highlighter.depends_on_cursor())
+		{
+			queue_move(term, self.rows_above(), cursor::MoveUp)?;
+			term.queue(Clear(FromCursorDown))?;
+			self.render(term)
+		} else {
+			self.set_cursor(term)
+		}
+	}
+	/// Move the cursor to the same column of the previous or next line of multi-line input.
+	/// Returns `false` if there is no such line.
+	fn move_vertically(&mut self, up: bool, term: &mut impl Write) -> io::Result<bool> {
+		let graphemes = self.line.graphemes(true).collect::<Vec<_>>();
+		let cursor = self.line_cursor_grapheme;
+		let line_start = |end: usize| {
+			graphemes[..end]
+				.iter()
+				.rposition(|g| *g == "\n")
+				.map_or(0, |i| i + 1)
+		};
+		let start = line_start(cursor);
+		let target_start = if up {
+			match start {
+				0 => return Ok(false),
+				start => line_start(start - 1),
+			}
+		} else {
+			match graphemes[cursor..].iter().position(|g| *g == "\n") {
+				Some(i) => cursor + i + 1,
+				None => return Ok(false),
+			}
+		};
+
+		// Find the grapheme in the target line that is at the cursor's column
+		let column = UnicodeWidthStr::width(&graphemes[start..cursor].concat()[..]);
+		let mut target = target_start;
+		let mut target_column = 0;
+		while let Some(grapheme) = graphemes.get(target).filter(|g| **g != "\n") {
+			target_column += UnicodeWidthStr::width(*grapheme);
+			if target_column > column {
+				break;
+			}
+			target += 1;
+		}
+		self.update_cursor(target as isize - cursor as isize, term)?;
+		Ok(true)
+	}
+	/// Grapheme index of the start of the logical line containing grapheme `pos`,
+	/// or of the previous line if `pos` is at a line start already
+	fn line_start(&self, pos: usize) -> usize {
+		let graphemes = self.line.graphemes(true).take(pos).collect::<Vec<_>>();
+		match graphemes.iter().rposition(|g| *g == "\n") {
+			Some(i) if i + 1 == pos => i,
+			Some(i) => i + 1,
+			None => 0,
+		}
+	}
+	/// Grapheme index of the end of the logical line containing grapheme `pos`,
+	/// or of the next line if `pos` is at a line end already
+	fn line_end(&self, pos: usize) -> usize {
+		match self.line.graphemes(true).skip(pos).position(|g| g == "\n") {
+			Some(0) => pos + 1,
+			Some(i) => pos + i,
+			None => self.line.graphemes(true).count(),
+		}
+	}
+	/// Grapheme index of the start of the word before grapheme `pos`
+	fn word_start(&self, pos: usize, is_word: fn(&str) -> bool) -> usize {
+		let graphemes = self.line.graphemes(true).take(pos).collect::<Vec<_>>();
+		let mut start = graphemes.len();
+		while start > 0 && !is_word(graphemes[start - 1]) {
+			start -= 1;
+		}
+		while start > 0 && is_word(graphemes[start - 1]) {
+			start -= 1;
+		}
+		start
+	}
+	/// Grapheme index of the end of the word after grapheme `pos`
+	fn word_end(&self, pos: usize, is_word: fn(&str) -> bool) -> usize {
+		let mut graphemes = self.line.graphemes(true).skip(pos).peekable();
+		let mut end = pos;
+		while graphemes.next_if(|g| !is_word(g)).is_some() {
+			end += 1;
+		}
+		while graphemes.next_if(|g| is_word(g)).is_some() {
+			end += 1;
+		}
+		end
+	}
 	fn reset_cursor(&self, term: &mut impl Write) -> io::Result<()> {
-		self.move_to_beginning(term, self.current_column)
+		self.move_to_beginning(term, self.current_row)
 	}
 	fn set_cursor(&self, term: &mut impl Write) -> io::Result<()> {
-		self.move_from_beginning(term, self.current_column as u16)
+		self.move_from_beginning(term, self.current_row, self.current_column)
+	}
+	/// Number of rows drawn above the prompt, by live regions and status lines
+	fn rows_above(&self) -> u16 {
+		self.live_rows() + self.status_rows_above()
 	}
 	/// Clear current line
 	fn clear(&self, term: &mut impl Write) -> io::Result<()> {
-		self.move_to_beginning(term, self.current_column as u16)?;
+		self.move_to_beginning(term, self.current_row)?;
+		queue_move(term, self.rows_above(), cursor::MoveUp)?;
 		term.queue(Clear(FromCursorDown))?;
 		Ok(())
 	}
 	/// Render line
 	pub fn render(&self, term: &mut impl Write) -> io::Result<()> {
-		write!(term, "{}{}", self.prompt, self.line)?;
-		let line_len = self.prompt.len() + UnicodeWidthStr::width(&self.line[..]);
-		self.move_to_beginning(term, line_len as u16)?;
-		self.move_from_beginning(term, self.current_column)?;
+		self.write_live_regions(term)?;
+		self.write_status(StatusPosition::Above, term)?;
+		let mut end_row = match &self.widget {
+			Some(widget) => self.write_widget(widget, term)?,
+			None => self.write_line(term)?,
+		};
+		self.write_status(StatusPosition::Below, term)?;
+		if self.status_position == StatusPosition::Below {
+			end_row += self.status.len();
+		}
+		self.move_to_beginning(term, end_row as u16)?;
+		self.set_cursor(term)?;
 		Ok(())
 	}
+	/// Write the prompt and line, returning the row the text ends on
+	fn write_line(&self, term: &mut impl Write) -> io::Result<usize> {
+		let mut lines = ContinuationWriter {
+			term: &mut *term,
+			prompt: &self.continuation_prompt,
+		};
+		write!(lines, "{}", self.displayed_prompt())?;
+		match (&self.search, &self.highlighter) {
+			_ if self.password.is_some() => write!(lines, "{}", self.mask(&self.line))?,
+			(Some(search), _) => search.render_line(&self.line, &mut lines)?,
+			(None, Some(highlighter)) => {
+				let spans = highlighter.highlight(&self.line, self.line_cursor_grapheme);
+				write_highlighted(&mut lines, &self.line, spans)?;
+			}
+			(None, None) => write!(lines, "{}", self.line)?,
+		}
+		let mut end = self.layout_position(self.line.len());
+		if let Some(hint) = self.hint() {
+			write!(lines, "{}", hint.as_str().dim())?;
+			end = self.advance(end, &hint);
+		}
+		let (mut end_row, end_column) = end;
+		// Move to the next row if the text filled the last one exactly, so that the cursor can be placed there
+		if end_column >= self.term_size.0 as usize {
+			write!(term, "\r\n")?;
+			end_row += 1;
+		}
+		Ok(end_row)
+	}
 	/// Clear line and render
 	pub fn clear_and_render(&self, term: &mut impl Write) -> io::Result<()> {
 		self.clear(term)?;
@@ -119,14 +558,13 @@ impl LineState {
 		// If last written data was not newline, restore the cursor
 		if !self.last_line_completed {
 			term.queue(cursor::MoveUp(1))?
-				.queue(cursor::MoveToColumn(1))?
-				.queue(cursor::MoveRight(self.last_line_length as u16))?;
+				.queue(cursor::MoveToColumn(self.last_line_length as u16))?;
 		}
 
 		// Write data in a way that newlines also act as carriage returns
 		for line in data.split_inclusive(|b| *b == b'\n') {
 			term.write_all(line)?;
-			term.queue(cursor::MoveToColumn(1))?;
+			term.queue(cursor::MoveToColumn(0))?;
 		}
 
 		self.last_line_completed = data.ends_with(b"\n"); // Set whether data ends with newline
@@ -134,18 +572,28 @@ impl LineState {
 		// If data does not end with newline, save the cursor and write newline for prompt
 		// Usually data does end in newline due to the buffering of SharedWriter, but sometimes it may not (i.e. if .flush() is called)
 		if !self.last_line_completed {
-			self.last_line_length += data.len();
+			let last_line = match data.iter().rposition(|b| *b == b'\n') {
+				Some(newline) => {
+					self.last_line_length = 0;
+					&data[newline + 1..]
+				}
+				None => data,
+			};
+			self.last_line_length += display_width(&String::from_utf8_lossy(last_line));
 			// Make sure that last_line_length wraps around when doing multiple writes
 			if self.last_line_length >= self.term_size.0 as usize {
 				self.last_line_length %= self.term_size.0 as usize;
-				writeln!(term)?;
+				// Text continuing past the last column has wrapped already, but text ending there has yet to
+				if self.last_line_length == 0 {
+					writeln!(term)?;
+				}
 			}
 			writeln!(term)?; // Move to beginning of line and make new line
 		} else {
 			self.last_line_length = 0;
 		}
 
-		term.queue(cursor::MoveToColumn(1))?;
+		term.queue(cursor::MoveToColumn(0))?;
 
 		self.render(term)?;
 		Ok(())
@@ -154,202 +602,312 @@ impl LineState {
 		self.print_data(string.as_bytes(), term)?;
 		Ok(())
 	}
-	pub async fn handle_event(
+	pub fn handle_event(
 		&mut self,
 		event: Event,
 		term: &mut impl Write,
 	) -> Result<Option<String>, ReadlineError> {
-		// Update history entries
-		self.history.update().await;
-
-		match event {
-			// Regular Modifiers (None or Shift)
-			Event::Key(KeyEvent {
-				code,
-				modifiers: KeyModifiers::NONE,
-			})
-			| Event::Key(KeyEvent {
-				code,
-				modifiers: KeyModifiers::SHIFT,
-			}) => match code {
-				KeyCode::Enter => {
-					self.clear(term)?;
-					let line = std::mem::take(&mut self.line);
-					self.move_cursor(-100000)?;
-					self.render(term)?;
-
-					return Ok(Some(line));
-				}
-				// Delete character from line
-				KeyCode::Backspace => {
-					if let Some((pos, str)) = self.current_grapheme() {
-						self.clear(term)?;
+		self.previous_command = std::mem::take(&mut self.last_command);
 
-						let len = pos + str.len();
-						self.line.replace_range(pos..len, "");
-						self.move_cursor(-1)?;
+		if self.search.is_some() && self.handle_search_event(&event, term)? {
+			return Ok(None);
+		}
 
-						self.render(term)?;
-					}
+		// Passwords are never copied for undo
+		let before = match self.password {
+			Some(_) => None,
+			None => Some((self.line.clone(), self.line_cursor_grapheme)),
+		};
+		let result = self.handle_edit_event(event, term);
+		match (result.as_ref(), before) {
+			(Ok(None), Some((line, cursor))) if self.search.is_none() => {
+				self.record_edit(line, cursor)
+			}
+			(Ok(None), _) => {}
+			// The line was submitted or discarded
+			(_, before) => {
+				// Keep the undo history of the line a password replaced
+				if before.is_some() {
+					self.undo_stack.clear();
 				}
-				KeyCode::Left => {
-					self.reset_cursor(term)?;
-					self.move_cursor(-1)?;
-					self.set_cursor(term)?;
+				#[cfg(feature = "vi")]
+				if let Some(vi) = &mut self.vi {
+					vi.reset();
 				}
-				KeyCode::Right => {
-					self.reset_cursor(term)?;
-					self.move_cursor(1)?;
-					self.set_cursor(term)?;
+			}
+		}
+		result
+	}
+	fn handle_edit_event(
+		&mut self,
+		event: Event,
+		term: &mut impl Write,
+	) -> Result<Option<String>, ReadlineError> {
+		#[cfg(feature = "vi")]
+		let event = match self.handle_vi_event(event, term)? {
+			Some(event) => event,
+			None => return Ok(None),
+		};
+
+		let key = match event {
+			Event::Key(key) => key,
+			Event::Resize(x, y) => {
+				self.clear(term)?;
+				self.term_size = (x, y);
+				self.move_cursor(0)?;
+				self.render(term)?;
+				return Ok(None);
+			}
+			// Mouse and focus events, pastes are handled by `paste`
+			_ => return Ok(None),
+		};
+		self.pending_keys.push(key);
+		match self.keymap.lookup(&self.pending_keys) {
+			// Wait for the rest of the sequence
+			Lookup::Prefix => Ok(None),
+			Lookup::Bound => {
+				let keys = std::mem::take(&mut self.pending_keys);
+				self.run_binding(&keys, term)
+			}
+			// The sequence was cut short, run what was typed before this key if it is bound and then handle this key by itself
+			Lookup::Unbound if self.pending_keys.len() > 1 => {
+				let mut keys = std::mem::take(&mut self.pending_keys);
+				keys.pop();
+				if let Some(line) = self.run_binding(&keys, term)? {
+					return Ok(Some(line));
 				}
-				KeyCode::Home => {
-					self.reset_cursor(term)?;
-					self.move_cursor(-100000)?;
-					self.set_cursor(term)?;
+				self.handle_edit_event(Event::Key(key), term)
+			}
+			Lookup::Unbound => {
+				self.pending_keys.clear();
+				if let KeyEvent {
+					code: KeyCode::Char(c),
+					modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+					..
+				} = key
+				{
+					self.completion = None;
+					self.insert_char(c, term)?;
 				}
-				KeyCode::End => {
-					self.reset_cursor(term)?;
-					self.move_cursor(100000)?;
-					self.set_cursor(term)?;
+				Ok(None)
+			}
+		}
+	}
+	/// Run the command or callback bound to a key sequence, if any
+	fn run_binding(
+		&mut self,
+		keys: &[KeyEvent],
+		term: &mut impl Write,
+	) -> Result<Option<String>, ReadlineError> {
+		match self.keymap.get_mut(keys) {
+			Some(Binding::Command(command)) => {
+				let command = *command;
+				self.run_command(command, term)
+			}
+			// Callbacks would be handed the password in plain text
+			Some(Binding::Callback(_)) if self.password.is_some() => Ok(None),
+			Some(Binding::Callback(callback)) => {
+				let mut buffer = LineBuffer {
+					line: self.line.clone(),
+					cursor: self.line_cursor_grapheme,
+				};
+				callback(&mut buffer);
+				self.completion = None;
+				self.clear(term)?;
+				if buffer.line != self.line {
+					self.line = buffer.line;
+					self.history.reset_position();
 				}
-				KeyCode::Up => {
-					// search for next history item, replace line if found.
-					if let Some(line) = self.history.search_next(&self.line) {
-						self.line.clear();
-						self.line += line;
-						self.clear(term)?;
-						self.move_cursor(100000)?;
-						self.render(term)?;
-					}
+				let cursor = buffer.cursor.min(self.line.graphemes(true).count());
+				self.move_cursor(cursor as isize - self.line_cursor_grapheme as isize)?;
+				self.render(term)?;
+				Ok(None)
+			}
+			None => Ok(None),
+		}
+	}
+	/// Insert a typed character at the cursor
+	fn insert_char(&mut self, c: char, term: &mut impl Write) -> io::Result<()> {
+		self.last_command = LastCommand::Insert;
+		self.clear(term)?;
+		let prev_len = self.cluster_buffer.graphemes(true).count();
+		self.reserve_password(c.len_utf8());
+		self.cluster_buffer.push(c);
+		let new_len = self.cluster_buffer.graphemes(true).count();
+
+		let (g_pos, g_str) = self.current_grapheme().unwrap_or((0, ""));
+		let pos = g_pos + g_str.len();
+
+		self.line.insert(pos, c);
+		self.history.reset_position();
+
+		if prev_len != new_len {
+			self.move_cursor(1)?;
+			if prev_len > 0 {
+				if let Some((pos, str)) = self.cluster_buffer.grapheme_indices(true).next() {
+					let len = str.len();
+					self.cluster_buffer.replace_range(pos..len, "");
 				}
-				KeyCode::Down => {
-					// search for next history item, replace line if found.
-					if let Some(line) = self.history.search_previous(&self.line) {
-						self.line.clear();
-						self.line += line;
+			}
+		}
+		self.render(term)
+	}
+	fn run_command(
+		&mut self,
+		command: EditCommand,
+		term: &mut impl Write,
+	) -> Result<Option<String>, ReadlineError> {
+		// Any command other than completion ends the current completion
+		if !matches!(
+			command,
+			EditCommand::Complete | EditCommand::CompleteBackward
+		) {
+			self.completion = None;
+		}
+
+		let cursor = self.line_cursor_grapheme;
+		match command {
+			EditCommand::AcceptLine => {
+				// Continue on a new line if the input is incomplete
+				if let (Some(validator), None) = (&self.validator, &self.password) {
+					if validator.validate(&self.line) == ValidationResult::Incomplete {
 						self.clear(term)?;
-						self.move_cursor(100000)?;
+						self.replace_graphemes(cursor..cursor, "\n")?;
 						self.render(term)?;
+						return Ok(None);
 					}
 				}
-				// Add character to line and output
-				KeyCode::Char(c) => {
+				self.clear(term)?;
+				let line = std::mem::take(&mut self.line);
+				self.history.reset_position();
+				self.pending_completion = None;
+				self.move_cursor(-100000)?;
+				self.render(term)?;
+
+				return Ok(Some(line));
+			}
+			EditCommand::Interrupt => {
+				let line = format!("{}{}", self.displayed_prompt(), self.mask(&self.line));
+				self.print(&line, term)?;
+				self.line.clear();
+				self.pending_completion = None;
+				self.move_cursor(-10000)?;
+				self.clear_and_render(term)?;
+				return Err(ReadlineError::Interrupted);
+			}
+			EditCommand::EndOfFile => {
+				writeln!(term)?;
+				self.clear(term)?;
+				return Err(ReadlineError::Eof);
+			}
+			// Like in bash, Ctrl-D only ends the input on an empty line
+			EditCommand::DeleteCharOrEndOfFile if self.line.is_empty() => {
+				return self.run_command(EditCommand::EndOfFile, term);
+			}
+			EditCommand::DeleteChar | EditCommand::DeleteCharOrEndOfFile => {
+				if cursor < self.line.graphemes(true).count() {
 					self.clear(term)?;
-					let prev_len = self.cluster_buffer.graphemes(true).count();
-					self.cluster_buffer.push(c);
-					let new_len = self.cluster_buffer.graphemes(true).count();
-
-					let (g_pos, g_str) = self.current_grapheme().unwrap_or((0, ""));
-					let pos = g_pos + g_str.len();
-
-					self.line.insert(pos, c);
-
-					if prev_len != new_len {
-						self.move_cursor(1)?;
-						if prev_len > 0 {
-							if let Some((pos, str)) =
-								self.cluster_buffer.grapheme_indices(true).next()
-							{
-								let len = str.len();
-								self.cluster_buffer.replace_range(pos..len, "");
-							}
-						}
-					}
+					self.replace_graphemes(cursor..cursor + 1, "")?;
 					self.render(term)?;
 				}
-				_ => {}
-			},
-			// Control Keys
-			Event::Key(KeyEvent {
-				code,
-				modifiers: KeyModifiers::CONTROL,
-			}) => match code {
-				// End of transmission (CTRL-D)
-				KeyCode::Char('d') => {
-					writeln!(term)?;
+			}
+			// Delete character from line
+			EditCommand::BackwardDeleteChar => {
+				if let Some((pos, str)) = self.current_grapheme() {
 					self.clear(term)?;
-					return Err(ReadlineError::Eof);
-				}
-				// End of text (CTRL-C)
-				KeyCode::Char('c') => {
-					self.print(&format!("{}{}", self.prompt, self.line), term)?;
-					self.line.clear();
-					self.move_cursor(-10000)?;
-					self.clear_and_render(term)?;
-					return Err(ReadlineError::Interrupted);
-				}
-				// Clear all
-				KeyCode::Char('l') => {
-					term.queue(Clear(All))?.queue(cursor::MoveTo(0, 0))?;
-					self.clear_and_render(term)?;
-				}
-				// Clear to start
-				KeyCode::Char('u') => {
-					if let Some((pos, str)) = self.current_grapheme() {
-						let pos = pos + str.len();
-						self.line.drain(0..pos);
-						self.move_cursor(-10000)?;
-						self.clear_and_render(term)?;
-					}
+
+					let len = pos + str.len();
+					self.line.replace_range(pos..len, "");
+					self.history.reset_position();
+					self.move_cursor(-1)?;
+
+					self.render(term)?;
 				}
-				// Move to beginning
-				#[cfg(feature = "emacs")]
-				KeyCode::Char('a') => {
-					self.reset_cursor(term)?;
-					self.move_cursor(-100000)?;
-					self.set_cursor(term)?;
+			}
+			EditCommand::ForwardChar | EditCommand::EndOfLine if self.hint().is_some() => {
+				self.accept_hint(false, term)?
+			}
+			EditCommand::ForwardWord if self.hint().is_some() => self.accept_hint(true, term)?,
+			// Neither the history nor the completer are offered for passwords
+			EditCommand::HistorySearchBackward
+			| EditCommand::HistorySearchForward
+			| EditCommand::ReverseSearchHistory
+			| EditCommand::ForwardSearchHistory
+			| EditCommand::Complete
+			| EditCommand::CompleteBackward
+				if self.password.is_some() => {}
+			EditCommand::BackwardChar => self.update_cursor(-1, term)?,
+			EditCommand::ForwardChar => self.update_cursor(1, term)?,
+			EditCommand::BackwardWord => {
+				let start = self.word_start(cursor, is_word);
+				self.update_cursor(start as isize - cursor as isize, term)?;
+			}
+			EditCommand::ForwardWord => {
+				let end = self.word_end(cursor, is_word);
+				self.update_cursor(end as isize - cursor as isize, term)?;
+			}
+			EditCommand::BeginningOfLine => self.update_cursor(-100000, term)?,
+			EditCommand::EndOfLine => self.update_cursor(100000, term)?,
+			EditCommand::HistorySearchBackward => {
+				// Move to the previous line of multi-line input
+				if self.move_vertically(true, term)? {
+					return Ok(None);
 				}
-				// Move to end
-				#[cfg(feature = "emacs")]
-				KeyCode::Char('e') => {
-					self.reset_cursor(term)?;
+				// search for next history item, replace line if found.
+				if let Some(line) = self.history.search_next(&self.line) {
+					self.line.clear();
+					self.line += line;
+					self.clear(term)?;
 					self.move_cursor(100000)?;
-					self.set_cursor(term)?;
+					self.render(term)?;
 				}
-				// Move cursor left to previous word
-				KeyCode::Left => {
-					self.reset_cursor(term)?;
-					let count = self.line.graphemes(true).count();
-					let skip_count = count - self.line_cursor_grapheme;
-					if let Some((pos, _)) = self
-						.line
-						.grapheme_indices(true)
-						.rev()
-						.skip(skip_count)
-						.skip_while(|(_, str)| *str == " ")
-						.find(|(_, str)| *str == " ")
-					{
-						let change = pos as isize - self.line_cursor_grapheme as isize;
-						self.move_cursor(change + 1)?;
-					} else {
-						self.move_cursor(-10000)?
-					}
-					self.set_cursor(term)?;
+			}
+			EditCommand::HistorySearchForward => {
+				// Move to the next line of multi-line input
+				if self.move_vertically(false, term)? {
+					return Ok(None);
 				}
-				// Move cursor right to next word
-				KeyCode::Right => {
-					self.reset_cursor(term)?;
-					if let Some((pos, _)) = self
-						.line
-						.grapheme_indices(true)
-						.skip(self.line_cursor_grapheme)
-						.skip_while(|(_, c)| *c == " ")
-						.find(|(_, c)| *c == " ")
-					{
-						let change = pos as isize - self.line_cursor_grapheme as isize;
-						self.move_cursor(change)?;
-					} else {
-						self.move_cursor(10000)?;
-					};
-					self.set_cursor(term)?;
+				// search for previous history item, replace line if found.
+				if let Some(line) = self.history.search_previous(&self.line) {
+					self.line.clear();
+					self.line += line;
+					self.clear(term)?;
+					self.move_cursor(100000)?;
+					self.render(term)?;
 				}
-				_ => {}
-			},
-			Event::Resize(x, y) => {
-				self.term_size = (x, y);
+			}
+			EditCommand::ReverseSearchHistory => self.start_search(true, term)?,
+			EditCommand::ForwardSearchHistory => self.start_search(false, term)?,
+			// Complete the word under the cursor
+			EditCommand::Complete => self.complete(false, term)?,
+			EditCommand::CompleteBackward => self.complete(true, term)?,
+			// The completion already ended, and searches handle it themselves
+			EditCommand::Abort => {}
+			EditCommand::ClearScreen => {
+				term.queue(Clear(All))?.queue(cursor::MoveTo(0, 0))?;
 				self.clear_and_render(term)?;
 			}
-			_ => {}
+			EditCommand::UnixLineDiscard => {
+				self.kill(self.line_start(cursor)..cursor, true, term)?
+			}
+			EditCommand::KillLine => self.kill(cursor..self.line_end(cursor), false, term)?,
+			EditCommand::UnixWordRubout => {
+				self.kill(self.word_start(cursor, is_word)..cursor, true, term)?
+			}
+			EditCommand::KillWord => {
+				self.kill(cursor..self.word_end(cursor, is_alphanumeric), false, term)?
+			}
+			EditCommand::BackwardKillWord => {
+				self.kill(self.word_start(cursor, is_alphanumeric)..cursor, true, term)?
+			}
+			EditCommand::Yank => self.yank(term)?,
+			EditCommand::YankPop => self.yank_pop(term)?,
+			EditCommand::Undo => {
+				self.undo(term)?;
+			}
+			EditCommand::Redo => {
+				self.redo(term)?;
+			}
 		}
 		Ok(None)
 	}
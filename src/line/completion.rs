@@ -0,0 +1,196 @@
+use std::{
+	io::Write,
+	task::{Context, Poll},
+};
+
+use futures::{future::BoxFuture, prelude::*};
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use super::{LastCommand, LineState};
+use crate::{completion::common_prefix, Completion, ReadlineError};
+
+/// State of consecutive Tab presses
+pub struct CompletionState {
+	candidates: Vec<Completion>,
+	tab_count: usize,
+	// Candidate currently inserted while cycling
+	selected: Option<usize>,
+
+	// Line and cursor before completion started, restored before inserting a candidate when cycling
+	original_line: String,
+	original_cursor: usize,
+}
+
+/// Candidates that are still being computed by an async completer
+pub struct PendingCompletion {
+	future: BoxFuture<'static, Vec<Completion>>,
+	// Line and cursor at the time of the request, the result is stale if they changed
+	line: String,
+	cursor: usize,
+}
+
+impl LineState {
+	/// Handle Tab (or Shift-Tab when `backwards`): insert the common prefix, then list candidates, then cycle through them
+	pub(super) fn complete(
+		&mut self,
+		backwards: bool,
+		term: &mut impl Write,
+	) -> Result<(), ReadlineError> {
+		let state = match self.completion.take() {
+			Some(state) => state,
+			// Candidates are already on their way
+			None if self.pending_completion.is_some() => return Ok(()),
+			None => {
+				let mut future = match &mut self.completer {
+					Some(completer) => completer.complete(&self.line, self.line_cursor_grapheme),
+					None => return Ok(()),
+				};
+				match (&mut future).now_or_never() {
+					Some(candidates) => self.new_completion(candidates),
+					None => {
+						self.pending_completion = Some(PendingCompletion {
+							future,
+							line: self.line.clone(),
+							cursor: self.line_cursor_grapheme,
+						});
+						return Ok(());
+					}
+				}
+			}
+		};
+		self.complete_step(state, backwards, term)
+	}
+	/// Poll the candidates requested from an async completer
+	pub fn poll_pending_completion(&mut self, cx: &mut Context<'_>) -> Poll<Vec<Completion>> {
+		match &mut self.pending_completion {
+			Some(pending) => pending.future.poll_unpin(cx),
+			None => Poll::Pending,
+		}
+	}
+	/// Apply the candidates of an async completer, unless the line changed since they were requested
+	pub fn finish_pending_completion(
+		&mut self,
+		candidates: Vec<Completion>,
+		term: &mut impl Write,
+	) -> Result<(), ReadlineError> {
+		match self.pending_completion.take() {
+			Some(pending)
+				if pending.line == self.line && pending.cursor == self.line_cursor_grapheme =>
+			{
+				let (line, cursor) = (self.line.clone(), self.line_cursor_grapheme);
+				let state = self.new_completion(candidates);
+				self.complete_step(state, false, term)?;
+				self.last_command = LastCommand::Other;
+				self.record_edit(line, cursor);
+				Ok(())
+			}
+			_ => Ok(()),
+		}
+	}
+	fn new_completion(&self, candidates: Vec<Completion>) -> CompletionState {
+		CompletionState {
+			candidates,
+			tab_count: 0,
+			selected: None,
+			original_line: self.line.clone(),
+			original_cursor: self.line_cursor_grapheme,
+		}
+	}
+	fn complete_step(
+		&mut self,
+		mut state: CompletionState,
+		backwards: bool,
+		term: &mut impl Write,
+	) -> Result<(), ReadlineError> {
+		state.tab_count += 1;
+
+		match (state.candidates.len(), state.tab_count) {
+			(0, _) => {}
+			(1, 1) => {
+				let candidate = state.candidates[0].clone();
+				self.clear(term)?;
+				self.replace_graphemes(candidate.range, &candidate.replacement)?;
+				self.render(term)?;
+				// The completion is done, the next Tab completes afresh
+				return Ok(());
+			}
+			(_, 1) => {
+				if let Some(prefix) = common_prefix(&state.candidates, self.completion_ignore_case)
+				{
+					let range = state.candidates[0].range.clone();
+					let current = self
+						.line
+						.graphemes(true)
+						.skip(range.start)
+						.take(range.len())
+						.collect::<String>();
+					// When ignoring case, the prefix may differ from the typed text in case only
+					let differs_in_case = self.completion_ignore_case
+						&& prefix != current
+						&& prefix.to_lowercase() == current.to_lowercase();
+					if prefix.graphemes(true).count() > current.graphemes(true).count()
+						|| differs_in_case
+					{
+						let prefix = prefix.to_owned();
+						self.clear(term)?;
+						self.replace_graphemes(range, &prefix)?;
+						self.render(term)?;
+					}
+				}
+			}
+			(_, 2) => self.print_candidates(&state.candidates, term)?,
+			(count, _) => {
+				let selected = match (state.selected, backwards) {
+					(None, false) => 0,
+					(None, true) => count - 1,
+					(Some(i), false) => (i + 1) % count,
+					(Some(i), true) => (i + count - 1) % count,
+				};
+				state.selected = Some(selected);
+				let candidate = state.candidates[selected].clone();
+				self.clear(term)?;
+				self.line.clone_from(&state.original_line);
+				self.move_cursor(-100000)?;
+				self.move_cursor(state.original_cursor as isize)?;
+				self.replace_graphemes(candidate.range, &candidate.replacement)?;
+				self.render(term)?;
+			}
+		}
+		self.completion = Some(state);
+		Ok(())
+	}
+	/// List candidates in columns above the prompt
+	fn print_candidates(
+		&mut self,
+		candidates: &[Completion],
+		term: &mut impl Write,
+	) -> Result<(), ReadlineError> {
+		let width = candidates
+			.iter()
+			.map(|candidate| UnicodeWidthStr::width(candidate.display()))
+			.max()
+			.unwrap_or(0)
+			+ 2;
+		let columns = (self.term_size.0 as usize / width).max(1);
+		let rows = candidates.len().div_ceil(columns);
+
+		let mut output = String::new();
+		for row in 0..rows {
+			for column in 0..columns {
+				if let Some(candidate) = candidates.get(column * rows + row) {
+					let display = candidate.display();
+					output += display;
+					if column + 1 < columns {
+						let padding = width - UnicodeWidthStr::width(display);
+						output.extend(std::iter::repeat(' ').take(padding));
+					}
+				}
+			}
+			output.truncate(output.trim_end().len());
+			output.push('\n');
+		}
+		self.print(&output, term)
+	}
+}
@@ -0,0 +1,45 @@
+use std::io::{self, Write};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::LineState;
+
+impl LineState {
+	/// Suggested continuation of the line, from the hinter or else the history.
+	/// Only shown while the cursor is at the end of the line and no search is in progress, and never for passwords.
+	pub(super) fn hint(&self) -> Option<String> {
+		if self.line.is_empty()
+			|| self.search.is_some()
+			|| self.password.is_some()
+			|| self.line_cursor_grapheme < self.line.graphemes(true).count()
+		{
+			return None;
+		}
+		let hint = match &self.hinter {
+			Some(hinter) => hinter.hint(&self.line)?,
+			None if self.history_hints => self.history.hint(&self.line)?.to_owned(),
+			None => return None,
+		};
+		Some(hint).filter(|hint| !hint.is_empty() && !hint.contains('\n'))
+	}
+	/// Append the hint to the line, or only up to the end of its next word
+	pub(super) fn accept_hint(&mut self, word: bool, term: &mut impl Write) -> io::Result<()> {
+		let mut hint = match self.hint() {
+			Some(hint) => hint,
+			None => return Ok(()),
+		};
+		if word {
+			let start = hint
+				.find(|c: char| !c.is_whitespace())
+				.unwrap_or(hint.len());
+			let end = hint[start..]
+				.find(char::is_whitespace)
+				.map_or(hint.len(), |end| start + end);
+			hint.truncate(end);
+		}
+		let end = self.line.graphemes(true).count();
+		self.clear(term)?;
+		self.replace_graphemes(end..end, &hint)?;
+		self.render(term)
+	}
+}
@@ -0,0 +1,106 @@
+use std::{
+	collections::VecDeque,
+	io::{self, Write},
+	ops::Range,
+};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::LineState;
+
+/// Maximum number of kills remembered
+const KILL_RING_SIZE: usize = 32;
+
+/// Text removed by kill commands, most recent first
+#[derive(Default)]
+pub struct KillRing {
+	entries: VecDeque<String>,
+}
+impl KillRing {
+	/// Add killed text, merging it into the most recent entry if the previous command was a kill too
+	pub(super) fn add(&mut self, text: &str, backward: bool, merge: bool) {
+		match self.entries.front_mut() {
+			Some(entry) if merge && backward => entry.insert_str(0, text),
+			Some(entry) if merge => entry.push_str(text),
+			_ => {
+				self.entries.push_front(text.to_owned());
+				self.entries.truncate(KILL_RING_SIZE);
+			}
+		}
+	}
+	/// Most recently killed text
+	pub(super) fn latest(&self) -> Option<&str> {
+		self.entries.front().map(String::as_str)
+	}
+	/// Move to the next older entry, returning it
+	fn rotate(&mut self) -> Option<&str> {
+		self.entries.rotate_left(1.min(self.entries.len()));
+		self.entries.front().map(String::as_str)
+	}
+}
+
+/// Editing command run last, for commands that behave differently when repeated
+#[derive(Clone, Default, PartialEq, Eq)]
+pub enum LastCommand {
+	#[default]
+	Other,
+	Kill,
+	// Typed character, coalesced with the previous ones into a single undo step
+	Insert,
+	Undo,
+	// Graphemes inserted by the last yank
+	Yank(Range<usize>),
+}
+
+impl LineState {
+	/// Remove a range of graphemes from the line and add it to the kill ring
+	pub(super) fn kill(
+		&mut self,
+		range: Range<usize>,
+		backward: bool,
+		term: &mut impl Write,
+	) -> io::Result<()> {
+		if !range.is_empty() {
+			// Killed parts of passwords are discarded rather than kept around for yanking
+			if self.password.is_none() {
+				let text = self
+					.line
+					.graphemes(true)
+					.skip(range.start)
+					.take(range.len())
+					.collect::<String>();
+				let merge = self.previous_command == LastCommand::Kill;
+				self.kill_ring.add(&text, backward, merge);
+			}
+
+			self.clear(term)?;
+			self.replace_graphemes(range, "")?;
+			self.render(term)?;
+		}
+		self.last_command = LastCommand::Kill;
+		Ok(())
+	}
+	/// Insert the most recently killed text at the cursor
+	pub(super) fn yank(&mut self, term: &mut impl Write) -> io::Result<()> {
+		if let Some(text) = self.kill_ring.latest().map(str::to_owned) {
+			let cursor = self.line_cursor_grapheme;
+			self.clear(term)?;
+			self.replace_graphemes(cursor..cursor, &text)?;
+			self.render(term)?;
+			self.last_command = LastCommand::Yank(cursor..self.line_cursor_grapheme);
+		}
+		Ok(())
+	}
+	/// Replace the text inserted by the previous yank with the next older kill
+	pub(super) fn yank_pop(&mut self, term: &mut impl Write) -> io::Result<()> {
+		if let LastCommand::Yank(range) = self.previous_command.clone() {
+			if let Some(text) = self.kill_ring.rotate().map(str::to_owned) {
+				self.clear(term)?;
+				self.replace_graphemes(range.clone(), &text)?;
+				self.render(term)?;
+				self.last_command = LastCommand::Yank(range.start..self.line_cursor_grapheme);
+			}
+		}
+		Ok(())
+	}
+}
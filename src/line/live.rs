@@ -0,0 +1,67 @@
+use std::io::{self, Write};
+
+use super::{status::truncate, LineState};
+use crate::live::LiveUpdate;
+
+impl LineState {
+	/// Add a live region below the existing ones
+	pub fn add_live_region(
+		&mut self,
+		id: u64,
+		text: String,
+		term: &mut impl Write,
+	) -> io::Result<()> {
+		self.clear(term)?;
+		self.live_regions.push((id, text));
+		self.render(term)
+	}
+	/// Apply updates to the live regions, returning the text of the finished ones to be printed in their place.
+	/// Updates of regions that have finished already are ignored.
+	pub fn update_live_regions(
+		&mut self,
+		updates: Vec<(u64, LiveUpdate)>,
+		term: &mut impl Write,
+	) -> io::Result<String> {
+		self.clear(term)?;
+		let mut finished = String::new();
+		for (id, update) in updates {
+			let index = match self
+				.live_regions
+				.iter()
+				.position(|(region, _)| *region == id)
+			{
+				Some(index) => index,
+				None => continue,
+			};
+			match update {
+				LiveUpdate::Set(text) => self.live_regions[index].1 = text,
+				LiveUpdate::Finish(text) => {
+					let (_, last) = self.live_regions.remove(index);
+					finished += &text.unwrap_or(last);
+					finished.push('\n');
+				}
+			}
+		}
+		self.render(term)?;
+		Ok(finished)
+	}
+	/// Number of rows taken by the live regions
+	pub(super) fn live_rows(&self) -> u16 {
+		self.live_regions
+			.iter()
+			.map(|(_, text)| text.lines().count().max(1) as u16)
+			.sum()
+	}
+	/// Write the lines of the live regions, truncated so that they never wrap, each followed by a new line
+	pub(super) fn write_live_regions(&self, term: &mut impl Write) -> io::Result<()> {
+		for (_, text) in &self.live_regions {
+			if text.is_empty() {
+				write!(term, "\r\n")?;
+			}
+			for line in text.lines() {
+				write!(term, "{}\r\n", truncate(line, self.term_size.0 as usize))?;
+			}
+		}
+		Ok(())
+	}
+}
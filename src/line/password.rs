@@ -0,0 +1,112 @@
+use std::{
+	borrow::Cow,
+	io::{self, Write},
+};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+#[cfg(feature = "vi")]
+use super::ViState;
+use super::{LastCommand, LineState};
+
+/// Capacity reserved for a password, so that typing it doesn't reallocate the line and leave copies behind
+const PASSWORD_CAPACITY: usize = 256;
+
+/// Password being read, along with the line it replaced
+pub struct Password {
+	// Text displayed in place of each grapheme
+	mask: String,
+	prompt: String,
+	line: String,
+	cursor: usize,
+	#[cfg(feature = "vi")]
+	vi: Option<ViState>,
+}
+
+/// Overwrite text, including its spare capacity, before freeing it
+fn zeroize(text: &mut String) {
+	let mut bytes = std::mem::take(text).into_bytes();
+	bytes.resize(bytes.capacity(), 0);
+	bytes.fill(0);
+	// Keep the writes from being optimized away
+	std::hint::black_box(&bytes);
+}
+
+/// Make room for `additional` bytes by moving the text to a larger buffer, zeroizing the one it leaves
+fn reserve(text: &mut String, additional: usize) {
+	if text.capacity() - text.len() >= additional {
+		return;
+	}
+	let mut grown = String::with_capacity((text.len() + additional).max(2 * text.capacity()));
+	grown.push_str(text);
+	zeroize(&mut std::mem::replace(text, grown));
+}
+
+impl LineState {
+	/// Replace the prompt and line being edited with an empty password line, displaying `mask` for every grapheme typed.
+	/// The password is neither recorded for undo nor added to the kill ring, and history and completions are not offered.
+	pub fn start_password(
+		&mut self,
+		prompt: String,
+		mask: Option<char>,
+		term: &mut impl Write,
+	) -> io::Result<()> {
+		self.end_password(term)?;
+		self.clear(term)?;
+		self.end_search(false)?;
+		self.completion = None;
+		self.pending_completion = None;
+		self.password = Some(Password {
+			mask: mask.map(String::from).unwrap_or_default(),
+			prompt: std::mem::replace(&mut self.prompt, prompt),
+			cursor: self.line_cursor_grapheme,
+			line: std::mem::replace(&mut self.line, String::with_capacity(PASSWORD_CAPACITY)),
+			// Start the password in insert mode, and forget its keys afterwards
+			#[cfg(feature = "vi")]
+			vi: self.vi.as_mut().map(std::mem::take),
+		});
+		self.move_cursor(-100000)?;
+		self.render(term)
+	}
+	/// Zeroize what is left of the password and restore the prompt and line it replaced
+	pub fn end_password(&mut self, term: &mut impl Write) -> io::Result<()> {
+		let password = match self.password.take() {
+			Some(password) => password,
+			None => return Ok(()),
+		};
+		self.clear(term)?;
+		zeroize(&mut self.line);
+		zeroize(&mut self.cluster_buffer);
+		self.prompt = password.prompt;
+		self.line = password.line;
+		#[cfg(feature = "vi")]
+		if let (Some(vi), Some(saved)) = (&mut self.vi, password.vi) {
+			*vi = saved;
+		}
+		self.last_command = LastCommand::Other;
+		self.previous_command = LastCommand::Other;
+		self.move_cursor(-100000)?;
+		self.move_cursor(password.cursor as isize)?;
+		self.render(term)
+	}
+	/// Make room for `additional` bytes in the password without leaving a copy behind in a reallocated buffer
+	pub(super) fn reserve_password(&mut self, additional: usize) {
+		if self.password.is_some() {
+			reserve(&mut self.line, additional);
+			reserve(&mut self.cluster_buffer, additional);
+		}
+	}
+	/// Zeroize input that may be part of the password being read
+	pub fn forget_input(&self, mut text: String) {
+		if self.password.is_some() {
+			zeroize(&mut text);
+		}
+	}
+	/// Text as displayed on the line, masked while reading a password
+	pub fn mask<'a>(&self, text: &'a str) -> Cow<'a, str> {
+		match &self.password {
+			Some(password) => password.mask.repeat(text.graphemes(true).count()).into(),
+			None => text.into(),
+		}
+	}
+}
@@ -0,0 +1,273 @@
+use std::{
+	io::{self, Write},
+	ops::Range,
+};
+
+use crossterm::{
+	event::{Event, KeyCode, KeyEvent, KeyModifiers},
+	style::Stylize,
+};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::LineState;
+use crate::{keymap::Binding, EditCommand};
+
+/// State of an incremental history search (Ctrl-R / Ctrl-S)
+pub struct Search {
+	query: String,
+	// Index of the matched history entry
+	entry: Option<usize>,
+	// Byte range of the match within the line
+	matched: Option<Range<usize>>,
+	reverse: bool,
+	failed: bool,
+
+	// Line and cursor to restore when the search is aborted
+	original_line: String,
+	original_cursor: usize,
+
+	// State before each change of the query or of the match, restored one by one by Backspace
+	steps: Vec<Step>,
+}
+
+#[derive(Clone)]
+struct Step {
+	query: String,
+	entry: Option<usize>,
+	matched: Option<Range<usize>>,
+	reverse: bool,
+	failed: bool,
+}
+
+impl Search {
+	fn step(&self) -> Step {
+		Step {
+			query: self.query.clone(),
+			entry: self.entry,
+			matched: self.matched.clone(),
+			reverse: self.reverse,
+			failed: self.failed,
+		}
+	}
+	/// Text displayed in place of the prompt while searching
+	pub fn prompt(&self) -> String {
+		format!(
+			"({}{}i-search)'{}': ",
+			if self.failed { "failed " } else { "" },
+			if self.reverse { "reverse-" } else { "" },
+			self.query
+		)
+	}
+	/// Write the line, highlighting the matched text
+	pub fn render_line(&self, line: &str, term: &mut impl Write) -> io::Result<()> {
+		match &self.matched {
+			Some(range) if !range.is_empty() && range.end <= line.len() => write!(
+				term,
+				"{}{}{}",
+				&line[..range.start],
+				line[range.clone()].reverse(),
+				&line[range.end..]
+			),
+			_ => write!(term, "{}", line),
+		}
+	}
+}
+
+impl LineState {
+	/// Enter incremental search mode, or search for the next match if already searching
+	pub(super) fn start_search(&mut self, reverse: bool, term: &mut impl Write) -> io::Result<()> {
+		self.clear(term)?;
+		match &mut self.search {
+			Some(search) => {
+				let step = search.step();
+				if search.query.is_empty() {
+					search.query = self.last_search_query.clone();
+				}
+				// Nothing to search for yet
+				if search.query.is_empty() {
+					return self.render(term);
+				}
+				search.steps.push(step);
+				let start = match (search.entry, reverse) {
+					(Some(entry), true) => entry + 1,
+					(Some(entry), false) => match entry.checked_sub(1) {
+						Some(start) => start,
+						None => {
+							search.failed = true;
+							search.reverse = reverse;
+							self.move_cursor(0)?;
+							return self.render(term);
+						}
+					},
+					(None, true) => 0,
+					(None, false) => usize::MAX,
+				};
+				search.reverse = reverse;
+				self.search_from(start)?;
+			}
+			None => {
+				self.search = Some(Search {
+					query: String::new(),
+					entry: None,
+					matched: None,
+					reverse,
+					failed: false,
+					original_line: self.line.clone(),
+					original_cursor: self.line_cursor_grapheme,
+					steps: Vec::new(),
+				});
+				self.move_cursor(0)?;
+			}
+		}
+		self.render(term)
+	}
+	/// Add text to the query and search for it, starting at the current match
+	pub(super) fn extend_search(&mut self, text: &str, term: &mut impl Write) -> io::Result<()> {
+		self.clear(term)?;
+		if let Some(search) = &mut self.search {
+			search.steps.push(search.step());
+			search.query.push_str(text);
+			let start = match (search.entry, search.reverse) {
+				(Some(entry), _) => entry,
+				(None, true) => 0,
+				(None, false) => usize::MAX,
+			};
+			self.search_from(start)?;
+		}
+		self.render(term)
+	}
+	/// Search history for the current query, starting at the given entry
+	fn search_from(&mut self, start: usize) -> io::Result<()> {
+		let search = match &mut self.search {
+			Some(search) => search,
+			None => return Ok(()),
+		};
+		match self
+			.history
+			.search_substring(&search.query, start, search.reverse)
+		{
+			Some((entry, pos)) => {
+				search.entry = Some(entry);
+				search.matched = Some(pos..pos + search.query.len());
+				search.failed = false;
+				self.line.clear();
+				self.line += &self.history.entries[entry];
+				let cursor = self.line[..pos].graphemes(true).count();
+				self.move_cursor(-100000)?;
+				self.move_cursor(cursor as isize)?;
+			}
+			None => {
+				search.failed = true;
+				// The prompt changed, so update the cursor column
+				self.move_cursor(0)?;
+			}
+		}
+		Ok(())
+	}
+	/// Go back to the query and match before the last change, or to the original line before the first match
+	fn undo_search_step(&mut self) -> io::Result<()> {
+		let search = match &mut self.search {
+			Some(search) => search,
+			None => return Ok(()),
+		};
+		let step = match search.steps.pop() {
+			Some(step) => step,
+			None => return Ok(()),
+		};
+		let matched = match (step.entry, &step.matched) {
+			(Some(entry), Some(range)) => self.history.entries.get(entry).zip(Some(range.start)),
+			_ => None,
+		};
+		let (line, cursor) = match matched {
+			Some((entry, pos)) => (entry.clone(), entry[..pos].graphemes(true).count()),
+			None => (search.original_line.clone(), search.original_cursor),
+		};
+		search.query = step.query;
+		search.entry = step.entry;
+		search.matched = step.matched;
+		search.reverse = step.reverse;
+		search.failed = step.failed;
+		self.line = line;
+		self.move_cursor(-100000)?;
+		self.move_cursor(cursor as isize)
+	}
+	/// Leave search mode, keeping the matched line or restoring the original one
+	pub(super) fn end_search(&mut self, accept: bool) -> io::Result<()> {
+		if let Some(search) = self.search.take() {
+			self.last_search_query = search.query;
+			if !accept {
+				self.line = search.original_line;
+				self.move_cursor(-100000)?;
+				self.move_cursor(search.original_cursor as isize)?;
+			}
+			self.move_cursor(0)?;
+		}
+		Ok(())
+	}
+	/// Handle an event while in search mode.
+	/// Returns `false` if the event ended the search and should be handled as a regular event.
+	pub(super) fn handle_search_event(
+		&mut self,
+		event: &Event,
+		term: &mut impl Write,
+	) -> io::Result<bool> {
+		let key = match event {
+			Event::Key(key) => key,
+			Event::Resize(..) => return Ok(false),
+			// Mouse and focus events, pastes are handled by `paste`
+			_ => return Ok(true),
+		};
+		// Keys act on the search according to the command they are bound to
+		let command = match self.keymap.get_mut(&[*key]) {
+			Some(Binding::Command(command)) => Some(*command),
+			_ => None,
+		};
+		match (command, *key) {
+			(Some(EditCommand::ReverseSearchHistory), _) => self.start_search(true, term)?,
+			(Some(EditCommand::ForwardSearchHistory), _) => self.start_search(false, term)?,
+			// Abort search and restore the original line
+			(Some(EditCommand::Abort), _)
+			| (
+				_,
+				KeyEvent {
+					code: KeyCode::Esc,
+					modifiers: KeyModifiers::NONE,
+					..
+				},
+			) => {
+				self.clear(term)?;
+				self.end_search(false)?;
+				self.render(term)?;
+			}
+			// Abort search, then let the interrupt be handled as usual
+			(Some(EditCommand::Interrupt), _) => {
+				self.clear(term)?;
+				self.end_search(false)?;
+				self.render(term)?;
+				return Ok(false);
+			}
+			(Some(EditCommand::BackwardDeleteChar), _) => {
+				self.clear(term)?;
+				self.undo_search_step()?;
+				self.render(term)?;
+			}
+			(
+				_,
+				KeyEvent {
+					code: KeyCode::Char(c),
+					modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+					..
+				},
+			) => self.extend_search(c.encode_utf8(&mut [0; 4]), term)?,
+			// Any other key accepts the match and is handled as usual
+			_ => {
+				self.clear(term)?;
+				self.end_search(true)?;
+				self.render(term)?;
+				return Ok(false);
+			}
+		}
+		Ok(true)
+	}
+}
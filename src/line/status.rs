@@ -0,0 +1,96 @@
+use std::io::{self, Write};
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use super::LineState;
+
+/// Where the status lines are shown, relative to the line being edited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StatusPosition {
+	Above,
+	#[default]
+	Below,
+}
+
+impl LineState {
+	/// Replace the status lines, or remove them if `None`
+	pub fn set_status(&mut self, status: Option<String>, term: &mut impl Write) -> io::Result<()> {
+		self.clear(term)?;
+		self.status = status
+			.map(|status| status.lines().map(str::to_owned).collect())
+			.unwrap_or_default();
+		self.render(term)
+	}
+	pub fn set_status_position(
+		&mut self,
+		position: StatusPosition,
+		term: &mut impl Write,
+	) -> io::Result<()> {
+		self.clear(term)?;
+		self.status_position = position;
+		self.render(term)
+	}
+	/// Number of rows taken by status lines above the prompt
+	pub(super) fn status_rows_above(&self) -> u16 {
+		match self.status_position {
+			StatusPosition::Above => self.status.len() as u16,
+			StatusPosition::Below => 0,
+		}
+	}
+	/// Write the status lines shown at `position`, truncated so that they never wrap.
+	/// Lines above the prompt end with a new line, lines below start with one.
+	pub(super) fn write_status(
+		&self,
+		position: StatusPosition,
+		term: &mut impl Write,
+	) -> io::Result<()> {
+		if position != self.status_position {
+			return Ok(());
+		}
+		for line in &self.status {
+			let line = truncate(line, self.term_size.0 as usize);
+			match position {
+				StatusPosition::Above => write!(term, "{}\r\n", line)?,
+				StatusPosition::Below => write!(term, "\r\n{}", line)?,
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Cut text down to the given display width, keeping ANSI escape sequences intact
+pub(super) fn truncate(text: &str, width: usize) -> String {
+	let mut truncated = String::new();
+	let mut used = 0;
+	let mut styled = false;
+	let mut graphemes = text.graphemes(true).peekable();
+	while let Some(grapheme) = graphemes.next() {
+		if grapheme == "\x1b" {
+			styled = true;
+			truncated.push_str(grapheme);
+			// Control sequences end with a byte in the range @ to ~, other escape sequences after a single character
+			if graphemes.next_if_eq(&"[").is_some() {
+				truncated.push('[');
+				for part in graphemes.by_ref() {
+					truncated.push_str(part);
+					if part.chars().all(|c| ('@'..='~').contains(&c)) {
+						break;
+					}
+				}
+			} else if let Some(part) = graphemes.next() {
+				truncated.push_str(part);
+			}
+			continue;
+		}
+		used += UnicodeWidthStr::width(grapheme);
+		if used > width {
+			break;
+		}
+		truncated.push_str(grapheme);
+	}
+	if styled {
+		truncated.push_str("\x1b[0m");
+	}
+	truncated
+}
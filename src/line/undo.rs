@@ -0,0 +1,90 @@
+use std::io::{self, Write};
+
+use super::{LastCommand, LineState};
+
+/// Lines and cursor positions to return to with undo and redo
+#[derive(Default)]
+pub struct UndoStack {
+	undo: Vec<(String, usize)>,
+	redo: Vec<(String, usize)>,
+}
+impl UndoStack {
+	pub fn clear(&mut self) {
+		self.undo.clear();
+		self.redo.clear();
+	}
+}
+
+impl LineState {
+	/// Record an edit that changed the line from `line` with the cursor at grapheme `cursor`.
+	/// Consecutive typed characters are undone together.
+	pub(super) fn record_edit(&mut self, line: String, cursor: usize) {
+		if line == self.line || self.last_command == LastCommand::Undo {
+			return;
+		}
+		self.undo_stack.redo.clear();
+		if self.last_command == LastCommand::Insert && self.previous_command == LastCommand::Insert
+		{
+			return;
+		}
+		self.undo_stack.undo.push((line, cursor));
+	}
+	/// Revert the last edit, returning `false` if there is nothing to undo
+	pub fn undo(&mut self, term: &mut impl Write) -> io::Result<bool> {
+		self.clear(term)?;
+		let undone = self.undo_step()?;
+		self.render(term)?;
+		Ok(undone)
+	}
+	/// Reapply the last undone edit, returning `false` if there is nothing to redo
+	pub fn redo(&mut self, term: &mut impl Write) -> io::Result<bool> {
+		self.clear(term)?;
+		let redone = self.redo_step()?;
+		self.render(term)?;
+		Ok(redone)
+	}
+	/// Revert the last edit without rendering
+	pub(super) fn undo_step(&mut self) -> io::Result<bool> {
+		self.last_command = LastCommand::Undo;
+		// The stack belongs to the line a password replaced
+		if self.password.is_some() {
+			return Ok(false);
+		}
+		match self.undo_stack.undo.pop() {
+			Some(state) => {
+				let current = self.restore(state)?;
+				self.undo_stack.redo.push(current);
+				Ok(true)
+			}
+			None => Ok(false),
+		}
+	}
+	/// Reapply the last undone edit without rendering
+	pub(super) fn redo_step(&mut self) -> io::Result<bool> {
+		self.last_command = LastCommand::Undo;
+		// The stack belongs to the line a password replaced
+		if self.password.is_some() {
+			return Ok(false);
+		}
+		match self.undo_stack.redo.pop() {
+			Some(state) => {
+				let current = self.restore(state)?;
+				self.undo_stack.undo.push(current);
+				Ok(true)
+			}
+			None => Ok(false),
+		}
+	}
+	/// Replace the line and cursor, returning the previous ones
+	fn restore(&mut self, (line, cursor): (String, usize)) -> io::Result<(String, usize)> {
+		let current = (
+			std::mem::replace(&mut self.line, line),
+			self.line_cursor_grapheme,
+		);
+		self.move_cursor(-100000)?;
+		self.move_cursor(cursor as isize)?;
+		self.history.reset_position();
+		self.completion = None;
+		Ok(current)
+	}
+}
@@ -0,0 +1,613 @@
+use std::io::Write;
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::LineState;
+use crate::ReadlineError;
+
+/// Key bindings used for editing the line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditMode {
+	/// Emacs style bindings, always in insert mode
+	Emacs,
+	/// Vi style bindings, with an insert and a normal mode
+	Vi,
+}
+
+/// Mode of the vi editing mode, passed to the mode indicator
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ViMode {
+	#[default]
+	Insert,
+	Normal,
+}
+
+/// State of the vi editing mode
+#[derive(Default)]
+pub struct ViState {
+	mode: ViMode,
+	// Keys of the normal mode command being typed
+	pending: Vec<KeyEvent>,
+	// Keys of the last change, repeated by `.`
+	last_change: Vec<KeyEvent>,
+	// Keys of the change in progress while in insert mode, if it was started by a command
+	recording: Option<Vec<KeyEvent>>,
+	replaying: bool,
+}
+impl ViState {
+	/// Start over in insert mode for the next line
+	pub fn reset(&mut self) {
+		self.mode = ViMode::Insert;
+		self.pending.clear();
+		self.recording = None;
+	}
+	pub fn mode(&self) -> ViMode {
+		self.mode
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Motion {
+	Left,
+	Right,
+	// Motions with `true` use WORDs delimited by whitespace only
+	WordForward(bool),
+	WordBackward(bool),
+	WordEnd(bool),
+	LineStart,
+	FirstNonBlank,
+	LineEnd,
+	Find {
+		target: char,
+		forward: bool,
+		// Stop before the target
+		till: bool,
+	},
+	// The whole line, for doubled operators like `dd`
+	Line,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+	Delete,
+	Change,
+	Yank,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+	Move(Motion),
+	Operate(Operator, Motion),
+	// Enter insert mode, after moving the cursor
+	Insert(Option<Motion>),
+	Put { before: bool },
+	Undo,
+	Repeat,
+	History { up: bool },
+}
+impl Command {
+	/// Whether the command changes the line and can be repeated with `.`
+	fn is_change(self) -> bool {
+		matches!(
+			self,
+			Command::Operate(Operator::Delete | Operator::Change, _)
+				| Command::Insert(_)
+				| Command::Put { .. }
+		)
+	}
+}
+
+enum Parsed<T> {
+	Incomplete,
+	Invalid,
+	Complete(T),
+}
+
+/// Largest count a command is repeated by, larger ones are cut down to it
+const MAX_COUNT: usize = 9999;
+
+/// Split off a count from the start of the keys
+fn parse_count(keys: &[char]) -> (usize, &[char]) {
+	// A leading zero is the line start motion
+	let digits = match keys.first() {
+		Some('1'..='9') => keys.iter().take_while(|c| c.is_ascii_digit()).count(),
+		_ => 0,
+	};
+	let count = keys[..digits]
+		.iter()
+		.fold(0usize, |count, c| {
+			count
+				.saturating_mul(10)
+				.saturating_add(*c as usize - '0' as usize)
+		})
+		.clamp(1, MAX_COUNT);
+	(count, &keys[digits..])
+}
+
+fn parse_motion(keys: &[char]) -> Parsed<Motion> {
+	let motion = match keys {
+		[] => return Parsed::Incomplete,
+		['h'] => Motion::Left,
+		['l' | ' '] => Motion::Right,
+		['w'] => Motion::WordForward(false),
+		['W'] => Motion::WordForward(true),
+		['b'] => Motion::WordBackward(false),
+		['B'] => Motion::WordBackward(true),
+		['e'] => Motion::WordEnd(false),
+		['E'] => Motion::WordEnd(true),
+		['0'] => Motion::LineStart,
+		['^'] => Motion::FirstNonBlank,
+		['$'] => Motion::LineEnd,
+		['f' | 't' | 'F' | 'T'] => return Parsed::Incomplete,
+		[kind @ ('f' | 't' | 'F' | 'T'), target] => Motion::Find {
+			target: *target,
+			forward: kind.is_lowercase(),
+			till: matches!(kind, 't' | 'T'),
+		},
+		_ => return Parsed::Invalid,
+	};
+	Parsed::Complete(motion)
+}
+
+/// Parse the keys of a normal mode command into a count and the command
+fn parse_command(keys: &[char]) -> Parsed<(usize, Command)> {
+	let (count, rest) = parse_count(keys);
+	let command = match rest {
+		[] => return Parsed::Incomplete,
+		[operator @ ('d' | 'c' | 'y'), rest @ ..] => {
+			let (motion_count, rest) = parse_count(rest);
+			let motion = match rest {
+				[key] if key == operator => Motion::Line,
+				rest => match parse_motion(rest) {
+					Parsed::Complete(motion) => motion,
+					Parsed::Incomplete => return Parsed::Incomplete,
+					Parsed::Invalid => return Parsed::Invalid,
+				},
+			};
+			let operator = match operator {
+				'd' => Operator::Delete,
+				'c' => Operator::Change,
+				_ => Operator::Yank,
+			};
+			return Parsed::Complete((
+				(count * motion_count).min(MAX_COUNT),
+				Command::Operate(operator, motion),
+			));
+		}
+		['x'] => Command::Operate(Operator::Delete, Motion::Right),
+		['X'] => Command::Operate(Operator::Delete, Motion::Left),
+		['s'] => Command::Operate(Operator::Change, Motion::Right),
+		['S'] => Command::Operate(Operator::Change, Motion::Line),
+		['D'] => Command::Operate(Operator::Delete, Motion::LineEnd),
+		['C'] => Command::Operate(Operator::Change, Motion::LineEnd),
+		['i'] => Command::Insert(None),
+		['a'] => Command::Insert(Some(Motion::Right)),
+		['I'] => Command::Insert(Some(Motion::FirstNonBlank)),
+		['A'] => Command::Insert(Some(Motion::LineEnd)),
+		['p'] => Command::Put { before: false },
+		['P'] => Command::Put { before: true },
+		['u'] => Command::Undo,
+		['.'] => Command::Repeat,
+		['k'] => Command::History { up: true },
+		['j'] => Command::History { up: false },
+		rest => match parse_motion(rest) {
+			Parsed::Complete(motion) => Command::Move(motion),
+			Parsed::Incomplete => return Parsed::Incomplete,
+			Parsed::Invalid => return Parsed::Invalid,
+		},
+	};
+	Parsed::Complete((count, command))
+}
+
+/// Class of a grapheme for word motions: blank, word characters or other characters
+fn class(grapheme: &str, big_word: bool) -> u8 {
+	if grapheme.chars().all(char::is_whitespace) {
+		0
+	} else if big_word || grapheme.chars().any(|c| c.is_alphanumeric() || c == '_') {
+		1
+	} else {
+		2
+	}
+}
+
+/// Start and end of the logical line containing grapheme `pos`, the end being the index of its newline
+fn line_bounds(graphemes: &[&str], pos: usize) -> (usize, usize) {
+	let start = graphemes[..pos]
+		.iter()
+		.rposition(|g| *g == "\n")
+		.map_or(0, |i| i + 1);
+	let end = graphemes[pos..]
+		.iter()
+		.position(|g| *g == "\n")
+		.map_or(graphemes.len(), |i| pos + i);
+	(start, end)
+}
+
+/// Target of a single motion from grapheme `pos`, and whether an operator includes the target grapheme
+fn motion_target(graphemes: &[&str], motion: Motion, pos: usize) -> Option<(usize, bool)> {
+	let len = graphemes.len();
+	let (start, end) = line_bounds(graphemes, pos);
+	let target = match motion {
+		Motion::Left => (pos.saturating_sub(1).max(start), false),
+		Motion::Right => ((pos + 1).min(end), false),
+		Motion::WordForward(big) => {
+			let mut pos = pos;
+			let current = graphemes.get(pos).map_or(0, |g| class(g, big));
+			if current != 0 {
+				while pos < len && class(graphemes[pos], big) == current {
+					pos += 1;
+				}
+			}
+			while pos < len && class(graphemes[pos], big) == 0 {
+				pos += 1;
+			}
+			(pos, false)
+		}
+		Motion::WordBackward(big) => {
+			let mut pos = pos;
+			while pos > 0 && class(graphemes[pos - 1], big) == 0 {
+				pos -= 1;
+			}
+			if let Some(current) = pos.checked_sub(1).map(|i| class(graphemes[i], big)) {
+				while pos > 0 && class(graphemes[pos - 1], big) == current {
+					pos -= 1;
+				}
+			}
+			(pos, false)
+		}
+		Motion::WordEnd(big) => {
+			let mut pos = pos + 1;
+			while pos < len && class(graphemes[pos], big) == 0 {
+				pos += 1;
+			}
+			if pos >= len {
+				return Some((len.saturating_sub(1), true));
+			}
+			let current = class(graphemes[pos], big);
+			while pos + 1 < len && class(graphemes[pos + 1], big) == current {
+				pos += 1;
+			}
+			(pos, true)
+		}
+		Motion::LineStart => (start, false),
+		Motion::FirstNonBlank => {
+			let blanks = graphemes[start..end]
+				.iter()
+				.take_while(|g| class(g, true) == 0)
+				.count();
+			(start + blanks, false)
+		}
+		Motion::LineEnd => (end, false),
+		Motion::Find {
+			target,
+			forward,
+			till,
+		} => {
+			let target = target.encode_utf8(&mut [0; 4]).to_owned();
+			if forward {
+				let found = pos
+					+ 1 + graphemes
+					.get(pos + 1..end)?
+					.iter()
+					.position(|g| *g == target)?;
+				// Nothing is left to operate on if the character is right after the cursor
+				(
+					if till { found - 1 } else { found },
+					!till || found - 1 > pos,
+				)
+			} else {
+				let found = start + graphemes[start..pos].iter().rposition(|g| *g == target)?;
+				(if till { found + 1 } else { found }, false)
+			}
+		}
+		Motion::Line => (end, false),
+	};
+	Some(target)
+}
+
+/// Position of the cursor in normal mode, which rests on a grapheme rather than after the end of the line
+fn normal_cursor(graphemes: &[&str], pos: usize) -> usize {
+	let (start, end) = line_bounds(graphemes, pos);
+	if pos >= end && end > start {
+		end - 1
+	} else {
+		pos
+	}
+}
+
+impl LineState {
+	/// Switch between emacs and vi bindings, starting vi mode in insert mode
+	pub fn set_edit_mode(&mut self, mode: EditMode, term: &mut impl Write) -> std::io::Result<()> {
+		self.clear(term)?;
+		self.vi = match mode {
+			EditMode::Emacs => None,
+			EditMode::Vi => Some(ViState::default()),
+		};
+		self.move_cursor(0)?;
+		self.render(term)
+	}
+	pub fn edit_mode(&self) -> EditMode {
+		match self.vi {
+			Some(_) => EditMode::Vi,
+			None => EditMode::Emacs,
+		}
+	}
+	/// Replace the text displayed in front of the prompt for each vi mode, redrawing the line in place
+	pub fn set_vi_mode_indicator(
+		&mut self,
+		indicator: Box<dyn Fn(ViMode) -> String + Send>,
+		term: &mut impl Write,
+	) -> std::io::Result<()> {
+		self.clear(term)?;
+		self.vi_mode_indicator = Some(indicator);
+		self.move_cursor(0)?;
+		self.render(term)
+	}
+	/// Handle an event in vi mode.
+	/// Returns the event to handle with the regular bindings, if any.
+	pub(super) fn handle_vi_event(
+		&mut self,
+		event: Event,
+		term: &mut impl Write,
+	) -> Result<Option<Event>, ReadlineError> {
+		let (vi, key) = match (&mut self.vi, event) {
+			(Some(vi), Event::Key(key)) => (vi, key),
+			(_, event) => return Ok(Some(event)),
+		};
+
+		if vi.mode == ViMode::Insert {
+			if key.code == KeyCode::Esc {
+				if let Some(mut keys) = vi.recording.take() {
+					keys.push(key);
+					vi.last_change = keys;
+				}
+				vi.mode = ViMode::Normal;
+				self.clear(term)?;
+				let graphemes = self.line.graphemes(true).collect::<Vec<_>>();
+				let cursor = self.line_cursor_grapheme;
+				if cursor > line_bounds(&graphemes, cursor).0 {
+					self.move_cursor(-1)?;
+				}
+				self.move_cursor(0)?;
+				self.render(term)?;
+				return Ok(None);
+			}
+			if let Some(keys) = &mut vi.recording {
+				match key.code {
+					// Repeating a change should never submit the line
+					KeyCode::Enter => vi.recording = None,
+					_ => keys.push(key),
+				}
+			}
+			return Ok(Some(Event::Key(key)));
+		}
+
+		match key {
+			KeyEvent {
+				code: KeyCode::Char(_),
+				modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+				..
+			} => {}
+			KeyEvent {
+				code: KeyCode::Esc, ..
+			} => {
+				vi.pending.clear();
+				return Ok(None);
+			}
+			KeyEvent {
+				code: KeyCode::Backspace,
+				modifiers: KeyModifiers::NONE,
+				..
+			} => {
+				vi.pending.clear();
+				return Ok(Some(Event::Key(KeyEvent::new(
+					KeyCode::Left,
+					KeyModifiers::NONE,
+				))));
+			}
+			// Submitting or discarding the line starts the next one in insert mode
+			KeyEvent {
+				code: KeyCode::Enter,
+				..
+			}
+			| KeyEvent {
+				code: KeyCode::Char('c'),
+				modifiers: KeyModifiers::CONTROL,
+				..
+			} => {
+				vi.reset();
+				return Ok(Some(Event::Key(key)));
+			}
+			_ => {
+				vi.pending.clear();
+				return Ok(Some(Event::Key(key)));
+			}
+		}
+		vi.pending.push(key);
+		let chars = vi
+			.pending
+			.iter()
+			.filter_map(|key| match key.code {
+				KeyCode::Char(c) => Some(c),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+		let (count, command) = match parse_command(&chars) {
+			Parsed::Incomplete => return Ok(None),
+			Parsed::Invalid => {
+				vi.pending.clear();
+				return Ok(None);
+			}
+			Parsed::Complete(command) => command,
+		};
+		let keys = std::mem::take(&mut vi.pending);
+		if command.is_change() && !vi.replaying {
+			vi.last_change.clone_from(&keys);
+		}
+
+		match command {
+			Command::History { up } => {
+				let code = if up { KeyCode::Up } else { KeyCode::Down };
+				Ok(Some(Event::Key(KeyEvent::new(code, KeyModifiers::NONE))))
+			}
+			Command::Repeat => {
+				let keys = vi.last_change.clone();
+				vi.replaying = true;
+				let result = (0..count).try_for_each(|_| {
+					keys.iter().try_for_each(|key| {
+						self.handle_edit_event(Event::Key(*key), term).map(drop)
+					})
+				});
+				if let Some(vi) = &mut self.vi {
+					vi.replaying = false;
+				}
+				result.map(|_| None)
+			}
+			command => {
+				self.clear(term)?;
+				self.run_vi_command(count, command, keys)?;
+				self.move_cursor(0)?;
+				self.render(term)?;
+				Ok(None)
+			}
+		}
+	}
+	/// Run a normal mode command without rendering
+	fn run_vi_command(
+		&mut self,
+		count: usize,
+		command: Command,
+		keys: Vec<KeyEvent>,
+	) -> std::io::Result<()> {
+		let cursor = self.line_cursor_grapheme;
+		let graphemes = self.line.graphemes(true).collect::<Vec<_>>();
+		// Moves stop early once the motion doesn't move any further
+		let target_from = |motion: Motion, from: usize, count: usize| {
+			let mut target = (from, false);
+			for i in 0..count {
+				match motion_target(&graphemes, motion, target.0)? {
+					next if i > 0 && next.0 == target.0 => break,
+					next => target = next,
+				}
+			}
+			Some(target)
+		};
+		let target = |motion: Motion| target_from(motion, cursor, count);
+		let mut insert = false;
+		let new_cursor = match command {
+			Command::Move(motion) => target(motion).map(|(pos, _)| pos),
+			Command::Insert(motion) => {
+				insert = true;
+				motion.and_then(&target).map(|(pos, _)| pos)
+			}
+			Command::Operate(operator, motion) => {
+				let range = match motion {
+					Motion::Line => {
+						let (mut start, _) = line_bounds(&graphemes, cursor);
+						let mut end = (1..count)
+							.fold(line_bounds(&graphemes, cursor).1, |end, _| {
+								line_bounds(&graphemes, (end + 1).min(graphemes.len())).1
+							});
+						// Deleting whole lines takes a newline with them
+						if operator == Operator::Delete {
+							if end < graphemes.len() {
+								end += 1;
+							} else {
+								start = start.saturating_sub(1);
+							}
+						}
+						Some(start..end)
+					}
+					// Like in vim, `cw` changes to the end of the word rather than up to the next one
+					Motion::WordForward(big)
+						if operator == Operator::Change
+							&& graphemes.get(cursor).is_some_and(|g| class(g, big) != 0) =>
+					{
+						let (start, end) = line_bounds(&graphemes, cursor);
+						let class_at = |pos: usize| graphemes.get(pos).map(|g| class(g, big));
+						// The cursor may already be at the end of the first word
+						let word_end = if class_at(cursor + 1) != class_at(cursor) {
+							target_from(Motion::WordEnd(big), cursor, count - 1)
+						} else {
+							target(Motion::WordEnd(big))
+						};
+						word_end
+							.map(|(end, _)| cursor..end + 1)
+							.map(|range| range.start.max(start)..range.end.min(end))
+					}
+					motion => target(motion).map(|(pos, inclusive)| {
+						let start = pos.min(cursor);
+						let end = pos.max(cursor) + inclusive as usize;
+						start..end.min(graphemes.len())
+					}),
+				};
+				match range {
+					Some(range) if !range.is_empty() => {
+						if self.password.is_none() {
+							let text = self
+								.line
+								.graphemes(true)
+								.skip(range.start)
+								.take(range.len())
+								.collect::<String>();
+							self.kill_ring.add(&text, false, false);
+						}
+						match operator {
+							Operator::Delete | Operator::Change => {
+								self.replace_graphemes(range, "")?;
+							}
+							// Like in vim, yanking backwards moves to the start of the text, yanking lines doesn't move
+							Operator::Yank if !matches!(motion, Motion::Line) => {
+								self.move_cursor(range.start as isize - cursor as isize)?;
+							}
+							Operator::Yank => {}
+						}
+					}
+					_ => {}
+				}
+				if operator == Operator::Change {
+					insert = true;
+				}
+				None
+			}
+			Command::Put { before } => {
+				let empty = graphemes.is_empty();
+				if let Some(text) = self.kill_ring.latest().map(|text| text.repeat(count)) {
+					let pos = if before || empty { cursor } else { cursor + 1 };
+					self.replace_graphemes(pos..pos, &text)?;
+					// Rest on the last grapheme inserted
+					self.move_cursor(-1)?;
+				}
+				None
+			}
+			Command::Undo => {
+				for _ in 0..count {
+					if !self.undo_step()? {
+						break;
+					}
+				}
+				None
+			}
+			Command::Repeat | Command::History { .. } => None,
+		};
+		if let Some(new_cursor) = new_cursor {
+			self.move_cursor(new_cursor as isize - cursor as isize)?;
+		}
+
+		if insert {
+			if let Some(vi) = &mut self.vi {
+				vi.mode = ViMode::Insert;
+				if !vi.replaying {
+					vi.recording = Some(keys);
+				}
+			}
+		} else {
+			let graphemes = self.line.graphemes(true).collect::<Vec<_>>();
+			let cursor = normal_cursor(&graphemes, self.line_cursor_grapheme);
+			self.move_cursor(cursor as isize - self.line_cursor_grapheme as isize)?;
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,286 @@
+use std::io::{self, Write};
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+use super::{display_width, status::truncate, LineState};
+use crate::ReadlineError;
+
+/// One-shot prompt shown in place of the prompt and line being edited
+pub enum Widget {
+	Confirm(Confirm),
+	Select(Select),
+	Key(KeyPrompt),
+}
+
+/// Yes or no question, with the answer given by Enter if any
+pub struct Confirm {
+	pub prompt: String,
+	pub default: Option<bool>,
+}
+
+/// Options to pick one of, listed below the prompt from the one at `scroll` on
+pub struct Select {
+	pub prompt: String,
+	pub options: Vec<String>,
+	pub selected: usize,
+	pub scroll: usize,
+}
+
+/// Prompt answered by any key
+pub struct KeyPrompt {
+	pub prompt: String,
+}
+
+/// Widget answered with a value of type `Answer`
+pub trait Ask: Into<Widget> {
+	type Answer;
+	/// This widget, if it is the one shown
+	fn shown(widget: &mut Widget) -> Option<&mut Self>;
+	/// Handle a key other than Ctrl-C, returning the answer once it is given
+	fn handle_key(&mut self, key: KeyEvent) -> Result<Option<Self::Answer>, ReadlineError>;
+	/// Answer given by a line of non-interactive input, if it is a valid one
+	fn answer_line(&self, line: &str) -> Option<Self::Answer>;
+}
+
+/// Esc interrupts and Ctrl-D ends the input, unless any key is asked for
+fn cancel(key: KeyEvent) -> Result<(), ReadlineError> {
+	match (key.code, key.modifiers) {
+		(KeyCode::Esc, _) => Err(ReadlineError::Interrupted),
+		(KeyCode::Char('d'), KeyModifiers::CONTROL) => Err(ReadlineError::Eof),
+		_ => Ok(()),
+	}
+}
+
+impl From<Confirm> for Widget {
+	fn from(confirm: Confirm) -> Self {
+		Widget::Confirm(confirm)
+	}
+}
+impl Ask for Confirm {
+	type Answer = bool;
+	fn shown(widget: &mut Widget) -> Option<&mut Self> {
+		match widget {
+			Widget::Confirm(confirm) => Some(confirm),
+			_ => None,
+		}
+	}
+	fn handle_key(&mut self, key: KeyEvent) -> Result<Option<bool>, ReadlineError> {
+		cancel(key)?;
+		Ok(match key.code {
+			KeyCode::Char('y' | 'Y') => Some(true),
+			KeyCode::Char('n' | 'N') => Some(false),
+			KeyCode::Enter => self.default,
+			_ => None,
+		})
+	}
+	fn answer_line(&self, line: &str) -> Option<bool> {
+		match line.trim().to_lowercase().as_str() {
+			"y" | "yes" => Some(true),
+			"n" | "no" => Some(false),
+			"" => self.default,
+			_ => None,
+		}
+	}
+}
+
+impl From<Select> for Widget {
+	fn from(select: Select) -> Self {
+		Widget::Select(select)
+	}
+}
+impl Ask for Select {
+	type Answer = usize;
+	fn shown(widget: &mut Widget) -> Option<&mut Self> {
+		match widget {
+			Widget::Select(select) => Some(select),
+			_ => None,
+		}
+	}
+	fn handle_key(&mut self, key: KeyEvent) -> Result<Option<usize>, ReadlineError> {
+		cancel(key)?;
+		let control = key.modifiers == KeyModifiers::CONTROL;
+		let (selected, last) = (self.selected, self.options.len().saturating_sub(1));
+		// Moving past either end wraps around to the other one
+		self.selected = match key.code {
+			KeyCode::Enter => return Ok(Some(selected)),
+			KeyCode::Up | KeyCode::BackTab => selected.checked_sub(1).unwrap_or(last),
+			KeyCode::Char('p') if control => selected.checked_sub(1).unwrap_or(last),
+			KeyCode::Char('k') if !control => selected.checked_sub(1).unwrap_or(last),
+			KeyCode::Down | KeyCode::Tab => (selected + 1) % (last + 1),
+			KeyCode::Char('n') if control => (selected + 1) % (last + 1),
+			KeyCode::Char('j') if !control => (selected + 1) % (last + 1),
+			KeyCode::Home | KeyCode::PageUp => 0,
+			KeyCode::End | KeyCode::PageDown => last,
+			_ => selected,
+		};
+		Ok(None)
+	}
+	/// Options are selected by their text or their number, starting at 1
+	fn answer_line(&self, line: &str) -> Option<usize> {
+		let line = line.trim();
+		self.options
+			.iter()
+			.position(|option| option == line)
+			.or_else(|| {
+				line.parse::<usize>()
+					.ok()
+					.filter(|number| (1..=self.options.len()).contains(number))
+					.map(|number| number - 1)
+			})
+	}
+}
+
+impl From<KeyPrompt> for Widget {
+	fn from(key: KeyPrompt) -> Self {
+		Widget::Key(key)
+	}
+}
+impl Ask for KeyPrompt {
+	type Answer = KeyEvent;
+	fn shown(widget: &mut Widget) -> Option<&mut Self> {
+		match widget {
+			Widget::Key(key) => Some(key),
+			_ => None,
+		}
+	}
+	fn handle_key(&mut self, key: KeyEvent) -> Result<Option<KeyEvent>, ReadlineError> {
+		Ok(Some(key))
+	}
+	/// The first character of the line, or Enter if it is empty
+	fn answer_line(&self, line: &str) -> Option<KeyEvent> {
+		let code = match line.trim().chars().next() {
+			Some(c) => KeyCode::Char(c),
+			None => KeyCode::Enter,
+		};
+		Some(KeyEvent::new(code, KeyModifiers::NONE))
+	}
+}
+
+impl Widget {
+	/// Text shown on the row of the cursor
+	pub fn prompt(&self) -> String {
+		match self {
+			Widget::Confirm(Confirm { prompt, default }) => {
+				let choices = match default {
+					Some(true) => "[Y/n]",
+					Some(false) => "[y/N]",
+					None => "[y/n]",
+				};
+				format!("{}{} ", prompt, choices)
+			}
+			Widget::Select(Select { prompt, .. }) | Widget::Key(KeyPrompt { prompt }) => {
+				prompt.clone()
+			}
+		}
+	}
+}
+
+impl LineState {
+	/// Hide the prompt and line behind a widget until `end_widget` is called
+	pub fn start_widget(&mut self, widget: Widget, term: &mut impl Write) -> io::Result<()> {
+		self.clear(term)?;
+		self.widget = Some(widget);
+		self.scroll_selection();
+		self.move_cursor(0)?;
+		self.render(term)
+	}
+	/// Remove the widget, showing the prompt and line again
+	pub fn end_widget(&mut self, term: &mut impl Write) -> io::Result<()> {
+		if self.widget.is_none() {
+			return Ok(());
+		}
+		self.clear(term)?;
+		self.widget = None;
+		self.move_cursor(0)?;
+		self.render(term)
+	}
+	/// Handle an event while a widget of type `W` is shown, returning the answer once it is given.
+	/// Ctrl-C interrupts any widget, Esc and Ctrl-D also interrupt or end the input unless any key is asked for.
+	pub fn handle_widget_event<W: Ask>(
+		&mut self,
+		event: Event,
+		term: &mut impl Write,
+	) -> Result<Option<W::Answer>, ReadlineError> {
+		let key = match event {
+			Event::Key(key) => key,
+			Event::Resize(x, y) => {
+				self.clear(term)?;
+				self.term_size = (x, y);
+				self.scroll_selection();
+				self.move_cursor(0)?;
+				self.render(term)?;
+				return Ok(None);
+			}
+			// Mouse and focus events, pastes are answered key by key
+			_ => return Ok(None),
+		};
+		if key.code == KeyCode::Char('c') && key.modifiers == KeyModifiers::CONTROL {
+			return Err(ReadlineError::Interrupted);
+		}
+		let widget = match self.widget.as_mut().and_then(W::shown) {
+			Some(widget) => widget,
+			None => return Ok(None),
+		};
+		let answer = widget.handle_key(key)?;
+		// Only the selection of a select widget changes before it is answered
+		if answer.is_none() && matches!(self.widget, Some(Widget::Select(_))) {
+			self.clear(term)?;
+			self.scroll_selection();
+			self.render(term)?;
+		}
+		Ok(answer)
+	}
+	/// Answer to the widget of type `W` given by a line of non-interactive input
+	pub fn widget_answer<W: Ask>(&mut self, line: &str) -> Option<W::Answer> {
+		W::shown(self.widget.as_mut()?)?.answer_line(line)
+	}
+	/// Prompt printed in front of lines echoed from non-interactive input, that of the widget if one is shown
+	pub fn plain_prompt(&self) -> String {
+		match &self.widget {
+			Some(widget) => widget.prompt(),
+			None => self.prompt.clone(),
+		}
+	}
+	/// Number of options of a select widget that fit on the terminal along with the prompt and the other lines
+	fn visible_options(&self) -> usize {
+		let used = 1 + self.live_rows() as usize + self.status.len();
+		(self.term_size.1 as usize).saturating_sub(used).max(1)
+	}
+	/// Scroll the options of a select widget just far enough for the selected one to be visible
+	fn scroll_selection(&mut self) {
+		let visible = self.visible_options();
+		if let Some(Widget::Select(Select {
+			selected, scroll, ..
+		})) = &mut self.widget
+		{
+			*scroll = (*scroll).clamp((*selected + 1).saturating_sub(visible), *selected);
+		}
+	}
+	/// Row and column of the cursor, at the end of the widget's prompt
+	pub(super) fn widget_cursor(&self, widget: &Widget) -> (u16, u16) {
+		let width = self.term_size.0.max(1) as usize;
+		(0, display_width(&widget.prompt()).min(width - 1) as u16)
+	}
+	/// Write the widget, truncated so that its lines never wrap, returning the row it ends on
+	pub(super) fn write_widget(&self, widget: &Widget, term: &mut impl Write) -> io::Result<usize> {
+		let width = self.term_size.0 as usize;
+		write!(term, "{}", truncate(&widget.prompt(), width))?;
+		let mut rows = 0;
+		if let Widget::Select(Select {
+			options,
+			selected,
+			scroll,
+			..
+		}) = widget
+		{
+			let visible = options.iter().enumerate().skip(*scroll);
+			for (i, option) in visible.take(self.visible_options()) {
+				let marker = if i == *selected { '>' } else { ' ' };
+				let line = format!("{} {}", marker, option);
+				write!(term, "\r\n{}", truncate(&line, width))?;
+				rows += 1;
+			}
+		}
+		Ok(rows)
+	}
+}
@@ -0,0 +1,150 @@
+use std::{
+	sync::mpsc as std_mpsc,
+	task::{Context, Poll},
+	thread,
+	time::{Duration, Instant},
+};
+
+use futures::{channel::mpsc, prelude::*};
+
+/// Change to a live region, sent by [`LiveRegion`] handles
+pub(crate) enum LiveUpdate {
+	Set(String),
+	// Final text, or the last one set if `None`
+	Finish(Option<String>),
+}
+
+/// Clonable handle for lines (e.g. a progress bar or spinner) that are updated in place between the output and the prompt,
+/// created with `Readline::add_live_region`.
+/// Updates are drawn the next time `readline()` is polled, at most once per interval set with `Readline::set_live_region_interval`.
+#[derive(Clone)]
+pub struct LiveRegion {
+	id: u64,
+	sender: mpsc::UnboundedSender<(u64, LiveUpdate)>,
+}
+impl LiveRegion {
+	/// Replace the text of the region, which may span several lines. Lines wider than the terminal are truncated.
+	pub fn set(&self, text: impl Into<String>) -> Option<()> {
+		self.send(LiveUpdate::Set(text.into()))
+	}
+	/// Remove the region, printing its last text into the scrollback. Later updates are ignored.
+	pub fn finish(&self) -> Option<()> {
+		self.send(LiveUpdate::Finish(None))
+	}
+	/// Remove the region, printing `text` into the scrollback in its place. Later updates are ignored.
+	pub fn finish_with(&self, text: impl Into<String>) -> Option<()> {
+		self.send(LiveUpdate::Finish(Some(text.into())))
+	}
+	fn send(&self, update: LiveUpdate) -> Option<()> {
+		self.sender.unbounded_send((self.id, update)).ok()
+	}
+}
+
+/// Updates of the live regions of a `Readline`, collected until they are due to be drawn
+pub(crate) struct LiveUpdates {
+	sender: mpsc::UnboundedSender<(u64, LiveUpdate)>,
+	receiver: mpsc::UnboundedReceiver<(u64, LiveUpdate)>,
+	next_id: u64,
+	pending: Vec<(u64, LiveUpdate)>,
+	pub interval: Duration,
+	last_draw: Option<Instant>,
+	// Started once updates first have to wait
+	timer: Option<Timer>,
+	timer_running: bool,
+}
+impl LiveUpdates {
+	pub fn new() -> Self {
+		let (sender, receiver) = mpsc::unbounded();
+		Self {
+			sender,
+			receiver,
+			next_id: 0,
+			pending: Vec::new(),
+			interval: Duration::from_millis(100),
+			last_draw: None,
+			timer: None,
+			timer_running: false,
+		}
+	}
+	/// Create the handle of a new region, returning its id
+	pub fn region(&mut self) -> (u64, LiveRegion) {
+		let id = self.next_id;
+		self.next_id += 1;
+		let region = LiveRegion {
+			id,
+			sender: self.sender.clone(),
+		};
+		(id, region)
+	}
+	/// Poll for the updates to draw. Updates setting the text of a region are combined and drawn at most once per interval,
+	/// finished regions are drawn right away.
+	pub fn poll_due(&mut self, cx: &mut Context<'_>) -> Poll<Vec<(u64, LiveUpdate)>> {
+		while let Poll::Ready(Some((id, update))) = self.receiver.poll_next_unpin(cx) {
+			self.queue(id, update);
+		}
+		loop {
+			if self.pending.is_empty() {
+				return Poll::Pending;
+			}
+			let finishing = self
+				.pending
+				.iter()
+				.any(|(_, update)| matches!(update, LiveUpdate::Finish(_)));
+			let next_draw = self.last_draw.map(|last| last + self.interval);
+			if finishing || next_draw.map_or(true, |next| Instant::now() >= next) {
+				self.last_draw = Some(Instant::now());
+				return Poll::Ready(std::mem::take(&mut self.pending));
+			}
+			// Wait for the interval to pass
+			let timer = self.timer.get_or_insert_with(Timer::spawn);
+			if !self.timer_running {
+				timer.wake_at(next_draw.unwrap_or_else(Instant::now));
+				self.timer_running = true;
+			}
+			match timer.expired.poll_next_unpin(cx) {
+				Poll::Ready(_) => self.timer_running = false,
+				Poll::Pending => return Poll::Pending,
+			}
+		}
+	}
+	/// Add an update, replacing an earlier update setting the text of the same region
+	fn queue(&mut self, id: u64, update: LiveUpdate) {
+		if let LiveUpdate::Set(text) = &update {
+			let earlier = self
+				.pending
+				.iter_mut()
+				.rev()
+				.find(|(region, _)| *region == id);
+			if let Some((_, LiveUpdate::Set(earlier))) = earlier {
+				earlier.clone_from(text);
+				return;
+			}
+		}
+		self.pending.push((id, update));
+	}
+}
+
+/// Thread that signals once a point in time has passed, as the crate doesn't depend on an async runtime
+struct Timer {
+	deadlines: std_mpsc::Sender<Instant>,
+	expired: mpsc::UnboundedReceiver<()>,
+}
+impl Timer {
+	fn spawn() -> Self {
+		let (deadlines, receiver) = std_mpsc::channel::<Instant>();
+		let (sender, expired) = mpsc::unbounded();
+		// Stops once the timer is dropped
+		thread::spawn(move || {
+			while let Ok(deadline) = receiver.recv() {
+				thread::sleep(deadline.saturating_duration_since(Instant::now()));
+				if sender.unbounded_send(()).is_err() {
+					break;
+				}
+			}
+		});
+		Self { deadlines, expired }
+	}
+	fn wake_at(&self, deadline: Instant) {
+		let _ = self.deadlines.send(deadline);
+	}
+}
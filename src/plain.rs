@@ -0,0 +1,56 @@
+use std::{
+	io::{self, stdin, BufRead, Write},
+	task::{Context, Poll},
+	thread,
+};
+
+use futures::channel::mpsc;
+
+use crate::{Backend, InputEvent};
+
+/// Standard input read line by line, used when it is not a terminal (e.g. when input is piped in)
+pub(crate) struct PlainInput {
+	pub lines: mpsc::UnboundedReceiver<io::Result<String>>,
+	/// Whether the prompt and each line read are printed
+	pub echo: bool,
+}
+impl PlainInput {
+	/// Start reading standard input on a separate thread, as reading it blocks
+	pub fn spawn() -> Self {
+		let (sender, lines) = mpsc::unbounded();
+		thread::spawn(move || {
+			for line in stdin().lock().lines() {
+				if sender.unbounded_send(line).is_err() {
+					break;
+				}
+			}
+		});
+		Self { lines, echo: false }
+	}
+}
+
+/// Backend without a terminal, discarding the rendered line.
+/// Its input is read through [`PlainInput`] instead.
+pub(crate) struct NoTerminal;
+impl Write for NoTerminal {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		Ok(buf.len())
+	}
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+impl Backend for NoTerminal {
+	fn poll_event(&mut self, _cx: &mut Context<'_>) -> Poll<Option<io::Result<InputEvent>>> {
+		Poll::Pending
+	}
+	fn size(&self) -> io::Result<(u16, u16)> {
+		Ok((80, 24))
+	}
+	fn enable_raw_mode(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+	fn disable_raw_mode(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
@@ -0,0 +1,21 @@
+/// Result of validating the line when Enter is pressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationResult {
+	/// The line is submitted
+	Complete,
+	/// A newline is inserted and editing continues on the next line
+	Incomplete,
+}
+
+/// Decides whether Enter submits the line or continues it on a new line, installed with `Readline::set_validator`
+pub trait Validator: Send {
+	fn validate(&self, line: &str) -> ValidationResult;
+}
+impl<F> Validator for F
+where
+	F: Fn(&str) -> ValidationResult + Send,
+{
+	fn validate(&self, line: &str) -> ValidationResult {
+		self(line)
+	}
+}
@@ -0,0 +1,125 @@
+mod support;
+
+use crossterm::event::KeyCode;
+use futures::channel::oneshot;
+use rustyline_async::Completion;
+use std::sync::{Arc, Mutex};
+use support::Harness;
+
+fn complete_from(
+	candidates: &'static [&'static str],
+) -> impl FnMut(&str, usize) -> Vec<Completion> + Send {
+	move |line: &str, _pos: usize| {
+		let len = line.chars().count();
+		candidates
+			.iter()
+			.map(|candidate| Completion::new(0..len, *candidate))
+			.collect()
+	}
+}
+
+type Requests = Arc<Mutex<Vec<(String, oneshot::Sender<Vec<Completion>>)>>>;
+
+/// Async completer whose requests are answered by the test, along with the line each was made for
+fn answered_by_test(harness: &mut Harness) -> Requests {
+	let requests = Requests::default();
+	let pending = requests.clone();
+	harness
+		.readline
+		.set_async_completer(move |line: &str, _pos: usize| {
+			let (sender, receiver) = oneshot::channel();
+			pending.lock().unwrap().push((line.to_owned(), sender));
+			async move { receiver.await.unwrap_or_default() }
+		});
+	requests
+}
+
+fn answer(requests: &Requests, candidates: &[&str]) {
+	let (line, sender) = requests.lock().unwrap().remove(0);
+	let len = line.chars().count();
+	let candidates = candidates
+		.iter()
+		.map(|candidate| Completion::new(0..len, *candidate))
+		.collect();
+	sender.send(candidates).unwrap();
+}
+
+#[test]
+fn applies_async_candidates_once_they_arrive() {
+	let mut harness = Harness::new("> ", 30, 5);
+	let requests = answered_by_test(&mut harness);
+	harness.type_str("gi");
+	harness.key(KeyCode::Tab);
+	assert_eq!(harness.screen.text(), ["> gi"]);
+	// Another Tab doesn't request the candidates again
+	harness.key(KeyCode::Tab);
+	assert_eq!(requests.lock().unwrap().len(), 1);
+
+	answer(&requests, &["git"]);
+	harness.run();
+	assert_eq!(harness.screen.text(), ["> git"]);
+	assert_eq!(harness.screen.cursor(), (5, 0));
+}
+
+#[test]
+fn discards_async_candidates_after_an_edit() {
+	let mut harness = Harness::new("> ", 30, 5);
+	let requests = answered_by_test(&mut harness);
+	harness.type_str("gi");
+	harness.key(KeyCode::Tab);
+	// Input is still handled while the candidates are on their way
+	harness.type_str("x");
+	assert_eq!(harness.screen.text(), ["> gix"]);
+
+	answer(&requests, &["git"]);
+	harness.run();
+	assert_eq!(harness.screen.text(), ["> gix"]);
+
+	// The next Tab requests candidates for the edited line
+	harness.key(KeyCode::Backspace);
+	harness.key(KeyCode::Tab);
+	answer(&requests, &["git", "gist"]);
+	harness.run();
+	assert_eq!(harness.screen.text(), ["> gi"]);
+	harness.key(KeyCode::Tab);
+	assert_eq!(harness.screen.text(), ["git   gist", "> gi"]);
+}
+
+#[test]
+fn ignores_case_of_characters_differing_in_length() {
+	let mut harness = Harness::new("> ", 30, 5);
+	harness
+		.readline
+		.set_completer(complete_from(&["\u{212A}x", "k"]));
+	harness.readline.set_completion_ignore_case(true);
+	harness.key(KeyCode::Tab);
+	assert_eq!(harness.screen.text(), ["> \u{212A}"]);
+	let line = harness.type_str("\n");
+	assert_eq!(line.unwrap().unwrap(), "\u{212A}");
+}
+
+#[test]
+fn completes_afresh_after_a_unique_candidate() {
+	let mut harness = Harness::new("> ", 30, 5);
+	harness.readline.set_completer(|line: &str, _pos: usize| {
+		let candidates: &[&str] = match line {
+			"gi" => &["git"],
+			_ => &["git status", "git stash"],
+		};
+		candidates
+			.iter()
+			.map(|candidate| Completion::new(0..line.chars().count(), *candidate))
+			.collect()
+	});
+	harness.type_str("gi");
+	harness.key(KeyCode::Tab);
+	assert_eq!(harness.screen.text(), ["> git"]);
+	// Completes the new line instead of listing "git"
+	harness.key(KeyCode::Tab);
+	assert_eq!(harness.screen.text(), ["> git sta"]);
+	harness.key(KeyCode::Tab);
+	assert_eq!(
+		harness.screen.text(),
+		["git status  git stash", "> git sta"]
+	);
+}
@@ -0,0 +1,47 @@
+mod support;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use rustyline_async::ReadlineError;
+use support::Harness;
+
+fn ctrl_d() -> KeyEvent {
+	KeyEvent::new(KeyCode::Char('d'), KeyModifiers::CONTROL)
+}
+
+#[test]
+fn deletes_the_grapheme_under_the_cursor() {
+	let mut harness = Harness::new("> ", 30, 5);
+	harness.type_str("ae\u{301}b");
+	harness.key(KeyCode::Home);
+	harness.key(KeyCode::Delete);
+	assert_eq!(harness.screen.text(), ["> e\u{301}b"]);
+	harness.keys(&[ctrl_d()]);
+	assert_eq!(harness.screen.text(), ["> b"]);
+	assert_eq!(harness.screen.cursor(), (2, 0));
+
+	// Nothing to delete at the end of the line
+	harness.key(KeyCode::End);
+	harness.key(KeyCode::Delete);
+	assert!(harness.keys(&[ctrl_d()]).is_none());
+	assert_eq!(harness.screen.text(), ["> b"]);
+}
+
+#[test]
+fn ctrl_d_ends_the_input_only_on_an_empty_line() {
+	let mut harness = Harness::new("> ", 30, 5);
+	harness.type_str("x");
+	harness.key(KeyCode::Left);
+	assert!(harness.keys(&[ctrl_d()]).is_none());
+	assert_eq!(harness.screen.text(), [">"]);
+
+	let result = harness.keys(&[ctrl_d()]).unwrap();
+	assert!(matches!(result, Err(ReadlineError::Eof)));
+}
+
+#[test]
+fn delete_never_ends_the_input() {
+	let mut harness = Harness::new("> ", 30, 5);
+	assert!(harness.key(KeyCode::Delete).is_none());
+	let line = harness.type_str("ok\n");
+	assert_eq!(line.unwrap().unwrap(), "ok");
+}
@@ -0,0 +1,82 @@
+mod support;
+
+use crossterm::event::KeyCode;
+use support::Harness;
+
+#[test]
+fn suggests_most_recent_history_entry() {
+	let mut harness = Harness::with_history(&["git status", "git commit -m fix"]);
+	harness.type_str("git");
+	assert_eq!(harness.screen.text(), ["> git commit -m fix"]);
+	assert_eq!(harness.screen.cursor(), (5, 0));
+
+	harness.type_str(" s");
+	assert_eq!(harness.screen.text(), ["> git status"]);
+	assert_eq!(harness.screen.cursor(), (7, 0));
+
+	harness.type_str("x");
+	assert_eq!(harness.screen.text(), ["> git sx"]);
+}
+
+#[test]
+fn hides_suggestion_away_from_the_end() {
+	let mut harness = Harness::with_history(&["cargo build"]);
+	harness.type_str("car");
+	assert_eq!(harness.screen.text(), ["> cargo build"]);
+	harness.key(KeyCode::Left);
+	assert_eq!(harness.screen.text(), ["> car"]);
+	assert_eq!(harness.screen.cursor(), (4, 0));
+	harness.key(KeyCode::End);
+	assert_eq!(harness.screen.text(), ["> cargo build"]);
+	assert_eq!(harness.screen.cursor(), (5, 0));
+}
+
+#[test]
+#[cfg(feature = "emacs")]
+fn accepts_whole_suggestion_or_next_word() {
+	use crossterm::event::{KeyEvent, KeyModifiers};
+	let mut harness = Harness::with_history(&["cargo test --workspace"]);
+	harness.type_str("ca");
+	harness.keys(&[KeyEvent::new(KeyCode::Char('f'), KeyModifiers::ALT)]);
+	assert_eq!(harness.screen.text(), ["> cargo test --workspace"]);
+	assert_eq!(harness.screen.cursor(), (7, 0));
+	harness.keys(&[KeyEvent::new(KeyCode::Char('f'), KeyModifiers::ALT)]);
+	assert_eq!(harness.screen.cursor(), (12, 0));
+
+	harness.key(KeyCode::Right);
+	assert_eq!(harness.screen.cursor(), (24, 0));
+	let line = harness.key(KeyCode::Enter);
+	assert_eq!(line.unwrap().unwrap(), "cargo test --workspace");
+}
+
+#[test]
+fn wrapped_suggestion_keeps_the_cursor_in_place() {
+	let mut harness = Harness::new("> ", 10, 5);
+	harness
+		.readline
+		.add_history_entry("abcdefghijklmnop".to_owned())
+		.unwrap();
+	harness.type_str("abc");
+	assert_eq!(harness.screen.text(), ["> abcdefgh", "ijklmnop"]);
+	assert_eq!(harness.screen.cursor(), (5, 0));
+	harness.type_str("d");
+	assert_eq!(harness.screen.text(), ["> abcdefgh", "ijklmnop"]);
+	assert_eq!(harness.screen.cursor(), (6, 0));
+}
+
+#[test]
+#[cfg(feature = "emacs")]
+fn hinter_replaces_history_suggestions() {
+	use crossterm::event::{KeyEvent, KeyModifiers};
+	let mut harness = Harness::with_history(&["help me"]);
+	harness
+		.readline
+		.set_hinter(|line: &str| "help".strip_prefix(line).map(str::to_owned))
+		.unwrap();
+	harness.type_str("he");
+	assert_eq!(harness.screen.text(), ["> help"]);
+	harness.keys(&[KeyEvent::new(KeyCode::Char('f'), KeyModifiers::CONTROL)]);
+	harness.type_str(" me");
+	assert_eq!(harness.screen.text(), ["> help me"]);
+	assert_eq!(harness.screen.cursor(), (9, 0));
+}
@@ -0,0 +1,101 @@
+mod support;
+
+use crossterm::event::KeyCode;
+use std::{fs, path::PathBuf};
+use support::Harness;
+
+fn temp_path(name: &str) -> PathBuf {
+	let path =
+		std::env::temp_dir().join(format!("rustyline-async-{}-{}", std::process::id(), name));
+	let _ = fs::remove_file(&path);
+	path
+}
+
+#[test]
+fn appends_entries_as_they_are_added() {
+	let path = temp_path("append");
+	let mut harness = Harness::new("> ", 30, 5);
+	harness
+		.readline
+		.set_history_append_file(Some(path.clone()))
+		.unwrap();
+	harness.readline.add_history_entry("ls".to_owned()).unwrap();
+	harness.readline.add_history_entry("ls".to_owned()).unwrap();
+	harness
+		.readline
+		.add_history_entry("echo a\nb".to_owned())
+		.unwrap();
+	// Written without reading another line
+	assert_eq!(fs::read_to_string(&path).unwrap(), "ls\necho a\\nb\n");
+	fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn keeps_entries_that_could_not_be_appended() {
+	// Its directory doesn't exist
+	let path = temp_path("missing").join("history");
+	let mut harness = Harness::new("> ", 30, 5);
+	harness
+		.readline
+		.set_history_append_file(Some(path))
+		.unwrap();
+	assert!(harness
+		.readline
+		.try_add_history_entry("ls".to_owned())
+		.is_err());
+	assert!(harness
+		.readline
+		.add_history_entry("cd".to_owned())
+		.is_none());
+
+	harness.key(KeyCode::Up);
+	assert_eq!(harness.screen.text(), ["> cd"]);
+	harness.key(KeyCode::Up);
+	assert_eq!(harness.screen.text(), ["> ls"]);
+}
+
+#[test]
+fn up_and_down_only_visit_entries_starting_with_the_typed_text() {
+	let mut harness = Harness::with_history(&["git status", "ls", "git commit", "git commit"]);
+	harness.type_str("git");
+	harness.key(KeyCode::Up);
+	assert_eq!(harness.screen.text(), ["> git commit"]);
+	assert_eq!(harness.screen.cursor(), (12, 0));
+	// Duplicates of the shown entry and entries with another prefix are skipped
+	harness.key(KeyCode::Up);
+	assert_eq!(harness.screen.text(), ["> git status"]);
+	harness.key(KeyCode::Up);
+	assert_eq!(harness.screen.text(), ["> git status"]);
+
+	harness.key(KeyCode::Down);
+	assert_eq!(harness.screen.text(), ["> git commit"]);
+	// Moving past the newest match restores the typed text
+	harness.key(KeyCode::Down);
+	assert_eq!(harness.screen.cursor(), (5, 0));
+	let line = harness.type_str("\n");
+	assert_eq!(line.unwrap().unwrap(), "git");
+}
+
+#[test]
+fn editing_an_entry_filters_by_the_edited_text() {
+	let mut harness = Harness::with_history(&["cat a", "cargo build", "cargo test", "ls"]);
+	harness.key(KeyCode::Up);
+	assert_eq!(harness.screen.text(), ["> ls"]);
+	harness.key(KeyCode::Up);
+	assert_eq!(harness.screen.text(), ["> cargo test"]);
+
+	for _ in 0.."rgo test".len() {
+		harness.key(KeyCode::Backspace);
+	}
+	harness.key(KeyCode::Up);
+	assert_eq!(harness.screen.text(), ["> cargo test"]);
+	harness.key(KeyCode::Up);
+	assert_eq!(harness.screen.text(), ["> cargo build"]);
+	harness.key(KeyCode::Up);
+	assert_eq!(harness.screen.text(), ["> cat a"]);
+	for _ in 0..3 {
+		harness.key(KeyCode::Down);
+	}
+	let line = harness.type_str("\n");
+	assert_eq!(line.unwrap().unwrap(), "ca");
+}
@@ -0,0 +1,98 @@
+mod support;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use rustyline_async::{Binding, EditCommand, Keymap};
+use support::Harness;
+
+fn ctrl(c: char) -> KeyEvent {
+	KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+}
+
+fn key(code: KeyCode) -> KeyEvent {
+	KeyEvent::new(code, KeyModifiers::NONE)
+}
+
+#[test]
+fn runs_callbacks_bound_to_key_sequences() {
+	let mut harness = Harness::new("> ", 30, 5);
+	harness.readline.bind(
+		&[ctrl('x'), ctrl('e')],
+		Binding::callback(|buffer| {
+			buffer.line = buffer.line.to_uppercase();
+			buffer.cursor = 1;
+		}),
+	);
+	harness.type_str("echo");
+	// Waits for the rest of the sequence
+	harness.keys(&[ctrl('x')]);
+	assert_eq!(harness.screen.text(), ["> echo"]);
+	harness.keys(&[ctrl('e')]);
+	assert_eq!(harness.screen.text(), ["> ECHO"]);
+	assert_eq!(harness.screen.cursor(), (3, 0));
+	let line = harness.type_str("\n");
+	assert_eq!(line.unwrap().unwrap(), "ECHO");
+}
+
+#[test]
+fn callback_cursor_is_kept_within_the_line() {
+	let mut harness = Harness::new("> ", 30, 5);
+	harness.readline.bind(
+		&[ctrl('t')],
+		Binding::callback(|buffer| {
+			buffer.line = "ab".to_owned();
+			buffer.cursor = 10;
+		}),
+	);
+	harness.type_str("long line");
+	harness.keys(&[ctrl('t')]);
+	assert_eq!(harness.screen.text(), ["> ab"]);
+	assert_eq!(harness.screen.cursor(), (4, 0));
+}
+
+#[test]
+fn keys_cutting_a_sequence_short_are_handled_by_themselves() {
+	let mut harness = Harness::new("> ", 30, 5);
+	harness
+		.readline
+		.bind(&[ctrl('x'), ctrl('e')], EditCommand::KillLine);
+	harness.type_str("ab");
+	// Ctrl-X isn't bound by itself, so only the character is inserted
+	harness.keys(&[ctrl('x')]);
+	harness.type_str("c");
+	assert_eq!(harness.screen.text(), ["> abc"]);
+
+	// A bound prefix runs before the key that follows it
+	harness
+		.readline
+		.bind(&[ctrl('x')], EditCommand::BeginningOfLine);
+	harness.keys(&[ctrl('x'), key(KeyCode::Right)]);
+	harness.type_str("-");
+	assert_eq!(harness.screen.text(), ["> a-bc"]);
+	harness.keys(&[ctrl('x'), ctrl('e')]);
+	assert_eq!(harness.screen.text(), ["> a-"]);
+}
+
+#[test]
+fn rebinding_and_unbinding_keys() {
+	let mut harness = Harness::new("> ", 30, 5);
+	harness.readline.bind(
+		&[key(KeyCode::Enter)],
+		"interrupt".parse::<EditCommand>().unwrap(),
+	);
+	harness.readline.unbind(&[key(KeyCode::Backspace)]);
+	harness.type_str("abc");
+	harness.key(KeyCode::Backspace);
+	assert_eq!(harness.screen.text(), ["> abc"]);
+	// Shift-Left falls back to the binding of Left
+	harness.keys(&[KeyEvent::new(KeyCode::Left, KeyModifiers::SHIFT)]);
+	assert_eq!(harness.screen.cursor(), (4, 0));
+	assert!(harness.key(KeyCode::Enter).unwrap().is_err());
+	assert_eq!(harness.screen.text(), ["> abc", ">"]);
+
+	// Characters are still inserted without any bindings
+	harness.readline.set_keymap(Keymap::empty());
+	harness.type_str("xy\n");
+	harness.key(KeyCode::Left);
+	assert_eq!(harness.screen.text(), ["> abc", "> xy"]);
+	assert_eq!(harness.screen.cursor(), (4, 1));
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "emacs")]
+
+mod support;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use support::Harness;
+
+fn ctrl(c: char) -> KeyEvent {
+	KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+}
+
+fn alt(c: char) -> KeyEvent {
+	KeyEvent::new(KeyCode::Char(c), KeyModifiers::ALT)
+}
+
+#[test]
+fn consecutive_kills_are_yanked_together() {
+	let mut harness = Harness::new("> ", 30, 5);
+	harness.type_str("one two three");
+	// Backward kills are prepended to the previous one
+	harness.keys(&[ctrl('w'), ctrl('w')]);
+	assert_eq!(harness.screen.text(), ["> one"]);
+	harness.keys(&[ctrl('y')]);
+	assert_eq!(harness.screen.text(), ["> one two three"]);
+
+	// Forward kills are appended, until another command runs in between
+	harness.keys(&[ctrl('a'), alt('d'), alt('d')]);
+	assert_eq!(harness.screen.text(), [">  three"]);
+	harness.keys(&[ctrl('e'), ctrl('y')]);
+	assert_eq!(harness.screen.text(), [">  threeone two"]);
+	harness.keys(&[ctrl('a'), ctrl('k')]);
+	harness.keys(&[ctrl('y'), ctrl('y')]);
+	assert_eq!(harness.screen.text(), [">  threeone two threeone two"]);
+}
+
+#[test]
+fn yank_pop_cycles_through_older_kills() {
+	let mut harness = Harness::new("> ", 30, 5);
+	harness.type_str("first");
+	harness.keys(&[ctrl('u')]);
+	harness.type_str("second");
+	harness.keys(&[ctrl('u')]);
+	harness.type_str("third");
+	harness.keys(&[ctrl('u')]);
+
+	harness.type_str("[");
+	harness.keys(&[ctrl('y')]);
+	harness.type_str("]");
+	assert_eq!(harness.screen.text(), ["> [third]"]);
+	// Only right after a yank
+	harness.keys(&[alt('y')]);
+	assert_eq!(harness.screen.text(), ["> [third]"]);
+
+	harness.keys(&[KeyEvent::new(KeyCode::Left, KeyModifiers::NONE), ctrl('y')]);
+	assert_eq!(harness.screen.text(), ["> [thirdthird]"]);
+	harness.keys(&[alt('y')]);
+	assert_eq!(harness.screen.text(), ["> [thirdsecond]"]);
+	assert_eq!(harness.screen.cursor(), (14, 0));
+	harness.keys(&[alt('y'), alt('y')]);
+	assert_eq!(harness.screen.text(), ["> [thirdthird]"]);
+}
@@ -0,0 +1,100 @@
+mod support;
+
+use std::time::Duration;
+
+use rustyline_async::StatusPosition;
+use support::Harness;
+
+#[test]
+fn live_regions_between_output_and_prompt() {
+	let mut harness = Harness::new("> ", 20, 8);
+	harness.readline.set_live_region_interval(Duration::ZERO);
+	let download = harness.readline.add_live_region("download 0%").unwrap();
+	let spinner = harness.readline.add_live_region("|").unwrap();
+	harness.type_str("ls");
+	assert_eq!(harness.screen.text(), ["download 0%", "|", "> ls"]);
+	assert_eq!(harness.screen.cursor(), (4, 2));
+
+	harness.print("log line\n");
+	download.set("download 50%").unwrap();
+	spinner.set("/").unwrap();
+	harness.run();
+	assert_eq!(
+		harness.screen.text(),
+		["log line", "download 50%", "/", "> ls"]
+	);
+	assert_eq!(harness.screen.cursor(), (4, 3));
+
+	download.finish_with("download done").unwrap();
+	harness.run();
+	assert_eq!(
+		harness.screen.text(),
+		["log line", "download done", "/", "> ls"]
+	);
+
+	// Updates after finishing are ignored
+	download.set("download 0%").unwrap();
+	spinner.finish().unwrap();
+	harness.run();
+	assert_eq!(
+		harness.screen.text(),
+		["log line", "download done", "/", "> ls"]
+	);
+	assert_eq!(harness.screen.cursor(), (4, 3));
+}
+
+#[test]
+fn live_regions_are_rate_limited() {
+	let mut harness = Harness::new("> ", 20, 8);
+	harness
+		.readline
+		.set_live_region_interval(Duration::from_secs(3600));
+	let progress = harness.readline.add_live_region("0%").unwrap();
+	progress.set("10%").unwrap();
+	harness.run();
+	assert_eq!(harness.screen.text(), ["10%", ">"]);
+
+	// Further updates wait for the interval to pass
+	progress.set("20%").unwrap();
+	progress.set("30%").unwrap();
+	harness.run();
+	assert_eq!(harness.screen.text(), ["10%", ">"]);
+
+	// Finishing is drawn right away, with the latest text
+	progress.finish().unwrap();
+	harness.run();
+	assert_eq!(harness.screen.text(), ["30%", ">"]);
+	assert_eq!(harness.screen.cursor(), (2, 1));
+}
+
+#[test]
+fn live_regions_with_multiple_lines_and_status() {
+	let mut harness = Harness::new("> ", 10, 8);
+	harness.readline.set_live_region_interval(Duration::ZERO);
+	harness
+		.readline
+		.set_status_position(StatusPosition::Above)
+		.unwrap();
+	harness
+		.readline
+		.set_status(Some("status".to_owned()))
+		.unwrap();
+	let jobs = harness
+		.readline
+		.add_live_region("job 1 [#####     ]\njob 2")
+		.unwrap();
+	harness.type_str("abcdefghijk");
+	assert_eq!(
+		harness.screen.text(),
+		["job 1 [###", "job 2", "status", "> abcdefgh", "ijk"]
+	);
+	assert_eq!(harness.screen.cursor(), (3, 4));
+
+	jobs.set("job 2").unwrap();
+	harness.run();
+	assert_eq!(
+		harness.screen.text(),
+		["job 2", "status", "> abcdefgh", "ijk"]
+	);
+	assert_eq!(harness.screen.cursor(), (3, 3));
+}
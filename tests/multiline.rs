@@ -0,0 +1,94 @@
+mod support;
+
+use crossterm::event::KeyCode;
+use rustyline_async::ValidationResult;
+use support::Harness;
+
+/// Harness whose lines are only complete once they end with a semicolon
+fn harness_with_validator(columns: u16) -> Harness {
+	let mut harness = Harness::new("> ", columns, 6);
+	harness
+		.readline
+		.set_validator(|line: &str| match line.trim_end().ends_with(';') {
+			true => ValidationResult::Complete,
+			false => ValidationResult::Incomplete,
+		});
+	harness
+		.readline
+		.set_continuation_prompt(". ".to_owned())
+		.unwrap();
+	harness
+}
+
+#[test]
+fn continues_incomplete_lines() {
+	let mut harness = harness_with_validator(20);
+	assert!(harness.type_str("select *\nfrom t\n").is_none());
+	assert_eq!(harness.screen.text(), ["> select *", ". from t", "."]);
+	assert_eq!(harness.screen.cursor(), (2, 2));
+
+	let line = harness.type_str("where x;\n");
+	assert_eq!(line.unwrap().unwrap(), "select *\nfrom t\nwhere x;");
+	// Every row of the submitted input is cleared
+	assert_eq!(harness.screen.text(), [">"]);
+	assert_eq!(harness.screen.cursor(), (2, 0));
+}
+
+#[test]
+fn renders_wrapped_rows_of_every_line() {
+	let mut harness = harness_with_validator(8);
+	harness.type_str("abcdefghij\nklmnopqrst");
+	assert_eq!(
+		harness.screen.text(),
+		["> abcdef", "ghij", ". klmnop", "qrst"]
+	);
+	assert_eq!(harness.screen.cursor(), (4, 3));
+
+	// Joining the lines rewraps them and clears the rows left over
+	for _ in 0.."klmnopqrst".len() {
+		harness.key(KeyCode::Left);
+	}
+	assert_eq!(harness.screen.cursor(), (2, 2));
+	harness.key(KeyCode::Backspace);
+	assert_eq!(harness.screen.text(), ["> abcdef", "ghijklmn", "opqrst"]);
+	assert_eq!(harness.screen.cursor(), (4, 1));
+}
+
+#[test]
+fn up_and_down_move_between_lines() {
+	let mut harness = harness_with_validator(20);
+	harness.type_str("first line\nab\nthird");
+	assert_eq!(harness.screen.cursor(), (7, 2));
+
+	// The column is kept where the target line is long enough
+	harness.key(KeyCode::Up);
+	assert_eq!(harness.screen.cursor(), (4, 1));
+	harness.key(KeyCode::Up);
+	assert_eq!(harness.screen.cursor(), (4, 0));
+	harness.type_str("X");
+	assert_eq!(harness.screen.text(), ["> fiXrst line", ". ab", ". third"]);
+	harness.key(KeyCode::Down);
+	harness.key(KeyCode::Down);
+	assert_eq!(harness.screen.cursor(), (4, 2));
+}
+
+#[test]
+fn up_moves_through_history_from_the_first_line() {
+	let mut harness = harness_with_validator(20);
+	harness
+		.readline
+		.add_history_entry("if x\nthen y;".to_owned());
+	harness.type_str("i");
+	harness.key(KeyCode::Up);
+	assert_eq!(harness.screen.text(), ["> if x", ". then y;"]);
+	assert_eq!(harness.screen.cursor(), (9, 1));
+
+	// Within the recalled entry Up moves to its first line first
+	harness.key(KeyCode::Up);
+	assert_eq!(harness.screen.cursor(), (6, 0));
+	harness.key(KeyCode::Down);
+	assert_eq!(harness.screen.cursor(), (6, 1));
+	// Past the last line, Down returns to the typed text
+	harness.key(KeyCode::Down);
+	assert_eq!(harness.screen.cursor(), (3, 0));
+}
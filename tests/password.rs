@@ -0,0 +1,180 @@
+mod support;
+
+use crossterm::event::{KeyCode, KeyModifiers};
+use futures::FutureExt;
+use support::Harness;
+
+#[test]
+fn masks_password_and_restores_line() {
+	let mut harness = Harness::new("> ", 30, 5);
+	harness.type_str("draft");
+	harness.key(KeyCode::Left);
+
+	harness.backend.push_str("pä日\n");
+	let password = harness
+		.readline
+		.read_password("Password: ".to_owned(), Some('*'))
+		.now_or_never();
+	assert_eq!(password.unwrap().unwrap(), "pä日");
+	harness.update_screen();
+	assert_eq!(harness.screen.text(), ["> draft"]);
+	assert_eq!(harness.screen.cursor(), (6, 0));
+
+	let line = harness.type_str("\n");
+	assert_eq!(line.unwrap().unwrap(), "draft");
+}
+
+#[test]
+fn renders_masked_password_while_typing() {
+	let mut harness = Harness::new("> ", 30, 5);
+	harness
+		.readline
+		.add_history_entry("secret things".to_owned())
+		.unwrap();
+	let mut password = Box::pin(
+		harness
+			.readline
+			.read_password("Password: ".to_owned(), Some('*')),
+	);
+	harness.backend.push_str("secret");
+	assert!(password.as_mut().now_or_never().is_none());
+	harness.screen.feed(&harness.backend.take_output());
+	// Without a suggestion from the history
+	assert_eq!(harness.screen.text(), ["Password: ******"]);
+	assert_eq!(harness.screen.cursor(), (16, 0));
+
+	// Nor recalling it
+	harness.backend.push_key(KeyCode::Up, KeyModifiers::NONE);
+	harness
+		.backend
+		.push_key(KeyCode::Backspace, KeyModifiers::NONE);
+	assert!(password.as_mut().now_or_never().is_none());
+	harness.screen.feed(&harness.backend.take_output());
+	assert_eq!(harness.screen.text(), ["Password: *****"]);
+
+	harness.backend.push_str("\n");
+	assert_eq!(password.now_or_never().unwrap().unwrap(), "secre");
+}
+
+#[test]
+fn hides_password_without_mask() {
+	let mut harness = Harness::new("> ", 30, 5);
+	let mut password = Box::pin(harness.readline.read_password("Token: ".to_owned(), None));
+	harness.backend.push_str("abc");
+	assert!(password.as_mut().now_or_never().is_none());
+	harness.screen.feed(&harness.backend.take_output());
+	assert_eq!(harness.screen.text(), ["Token:"]);
+	assert_eq!(harness.screen.cursor(), (7, 0));
+}
+
+#[test]
+fn prints_output_above_password() {
+	let mut harness = Harness::new("> ", 30, 5);
+	let mut password = Box::pin(
+		harness
+			.readline
+			.read_password("Password: ".to_owned(), Some('*')),
+	);
+	harness.backend.push_str("abc");
+	assert!(password.as_mut().now_or_never().is_none());
+	std::io::Write::write_all(&mut harness.writer, b"output\n").unwrap();
+	assert!(password.as_mut().now_or_never().is_none());
+	harness.screen.feed(&harness.backend.take_output());
+	assert_eq!(harness.screen.text(), ["output", "Password: ***"]);
+	assert_eq!(harness.screen.cursor(), (13, 1));
+
+	harness.backend.push_str("\n");
+	assert_eq!(password.now_or_never().unwrap().unwrap(), "abc");
+	harness.update_screen();
+	assert_eq!(harness.screen.text(), ["output", ">"]);
+}
+
+#[test]
+fn restores_line_after_cancelled_password() {
+	let mut harness = Harness::new("> ", 30, 5);
+	harness.type_str("ls");
+	harness.backend.push_str("abc");
+	let password = harness
+		.readline
+		.read_password("Password: ".to_owned(), Some('*'))
+		.now_or_never();
+	assert!(password.is_none());
+	harness.update_screen();
+	assert_eq!(harness.screen.text(), ["Password: ***"]);
+
+	let line = harness.type_str(" -l\n");
+	assert_eq!(line.unwrap().unwrap(), "ls -l");
+}
+
+#[test]
+#[cfg(feature = "emacs")]
+fn pasted_password_is_not_undone_into_line() {
+	use crossterm::event::KeyEvent;
+	let mut harness = Harness::new("> ", 30, 5);
+	harness.type_str("abc");
+	let mut password = Box::pin(
+		harness
+			.readline
+			.read_password("Password: ".to_owned(), Some('*')),
+	);
+	harness.backend.push_str("hun");
+	harness.backend.push_paste("ter2");
+	assert!(password.as_mut().now_or_never().is_none());
+	harness.screen.feed(&harness.backend.take_output());
+	assert_eq!(harness.screen.text(), ["Password: *******"]);
+	harness.backend.push_str("\n");
+	assert_eq!(password.now_or_never().unwrap().unwrap(), "hunter2");
+	harness.update_screen();
+
+	// Undo only reaches edits of the restored line
+	harness.keys(&[KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL)]);
+	assert_eq!(harness.screen.text(), [">"]);
+	let line = harness.type_str("\n");
+	assert_eq!(line.unwrap().unwrap(), "");
+}
+
+#[test]
+fn reads_passwords_longer_than_the_reserved_buffer() {
+	let mut harness = Harness::new("> ", 30, 5);
+	let typed = "a".repeat(300);
+	let pasted = "b".repeat(1000);
+	harness.backend.push_str(&typed);
+	harness.backend.push_paste(&pasted);
+	harness.backend.push_str("\n");
+	let password = harness
+		.readline
+		.read_password("Password: ".to_owned(), None)
+		.now_or_never();
+	assert_eq!(password.unwrap().unwrap(), typed + &pasted);
+}
+
+#[test]
+fn callbacks_are_not_run_for_passwords() {
+	use crossterm::event::KeyEvent;
+	use rustyline_async::Binding;
+	use std::sync::{Arc, Mutex};
+	let mut harness = Harness::new("> ", 30, 5);
+	let seen = Arc::new(Mutex::new(Vec::new()));
+	let recorded = seen.clone();
+	let ctrl_x = KeyEvent::new(KeyCode::Char('x'), KeyModifiers::CONTROL);
+	harness.readline.bind(
+		&[ctrl_x],
+		Binding::callback(move |buffer| recorded.lock().unwrap().push(buffer.line.clone())),
+	);
+
+	harness.backend.push_str("secret");
+	harness
+		.backend
+		.push_key(KeyCode::Char('x'), KeyModifiers::CONTROL);
+	harness.backend.push_str("\n");
+	let password = harness
+		.readline
+		.read_password("Password: ".to_owned(), Some('*'))
+		.now_or_never();
+	assert_eq!(password.unwrap().unwrap(), "secret");
+	assert!(seen.lock().unwrap().is_empty());
+
+	harness.type_str("ls");
+	harness.keys(&[ctrl_x]);
+	assert_eq!(*seen.lock().unwrap(), ["ls"]);
+}
@@ -0,0 +1,59 @@
+use std::{
+	env,
+	io::Write,
+	process::{Command, Stdio},
+};
+
+use futures::executor::block_on;
+use rustyline_async::{Readline, ReadlineError};
+
+/// Set for the copy of this test binary that reads piped input
+const CHILD: &str = "RUSTYLINE_ASYNC_PLAIN_CHILD";
+
+/// Reads its piped stdin with echo enabled, reporting every result.
+/// Does nothing unless run by `echoes_piped_lines_and_keeps_returning_eof`.
+#[test]
+fn read_piped_input() {
+	if env::var_os(CHILD).is_none() {
+		return;
+	}
+	let (mut readline, mut writer) = Readline::new("> ".to_owned()).unwrap();
+	assert!(!readline.is_interactive());
+	readline.set_echo(true);
+	for i in 0..4 {
+		if i == 2 {
+			writeln!(writer, "output").unwrap();
+		}
+		let result = block_on(readline.readline());
+		let mut stdout = std::io::stdout();
+		match result {
+			Ok(line) => writeln!(stdout, "line {:?}", line).unwrap(),
+			Err(ReadlineError::Eof) => writeln!(stdout, "eof").unwrap(),
+			Err(e) => writeln!(stdout, "error {}", e).unwrap(),
+		}
+	}
+}
+
+#[test]
+fn echoes_piped_lines_and_keeps_returning_eof() {
+	let mut child = Command::new(env::current_exe().unwrap())
+		.args(["read_piped_input", "--exact", "--nocapture", "--quiet"])
+		.env(CHILD, "1")
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.spawn()
+		.unwrap();
+	child
+		.stdin
+		.take()
+		.unwrap()
+		.write_all(b"first\nsecond\n")
+		.unwrap();
+	let output = child.wait_with_output().unwrap();
+	assert!(output.status.success());
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	// The test harness adds its own lines around the transcript
+	// Output of the `SharedWriter` is printed before the input ends
+	let expected = "> first\nline \"first\"\n> second\nline \"second\"\noutput\neof\neof\n";
+	assert!(stdout.contains(expected), "{:?}", stdout);
+}
@@ -0,0 +1,243 @@
+mod support;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use support::{Harness, Screen};
+
+#[test]
+fn screen_wraps_and_moves() {
+	let mut screen = Screen::new(4, 3);
+	screen.feed(b"abcd");
+	assert_eq!(screen.cursor(), (3, 0));
+	screen.feed(b"ef\x1b[1G\x1b[1A\x1b[2C\x1b[J");
+	assert_eq!(screen.lines(), ["ab", "", ""]);
+	assert_eq!(screen.cursor(), (2, 0));
+	screen.feed(b"\r\n\n\nx");
+	assert_eq!(screen.lines(), ["", "", "x"]);
+	assert_eq!(screen.scrollback, ["ab"]);
+}
+
+#[test]
+fn renders_prompt_and_line() {
+	let mut harness = Harness::new("> ", 20, 5);
+	assert_eq!(harness.screen.text(), [">"]);
+	assert_eq!(harness.screen.cursor(), (2, 0));
+
+	harness.type_str("hello");
+	assert_eq!(harness.screen.text(), ["> hello"]);
+	assert_eq!(harness.screen.cursor(), (7, 0));
+
+	harness.keys(&[
+		KeyEvent::new(KeyCode::Left, KeyModifiers::NONE),
+		KeyEvent::new(KeyCode::Left, KeyModifiers::NONE),
+	]);
+	harness.type_str("XY");
+	assert_eq!(harness.screen.text(), ["> helXYlo"]);
+	assert_eq!(harness.screen.cursor(), (7, 0));
+}
+
+#[test]
+fn wraps_long_lines() {
+	for width in [5, 7, 10, 13] {
+		let mut harness = Harness::new("> ", width, 6);
+		let text = "abcdefghijklmnopq";
+		harness.type_str(text);
+		let rendered = format!("> {}", text);
+		let expected = rendered
+			.as_bytes()
+			.chunks(width as usize)
+			.map(|row| String::from_utf8(row.to_vec()).unwrap())
+			.collect::<Vec<_>>();
+		assert_eq!(harness.screen.text(), expected, "width {}", width);
+		let len = rendered.len() as u16;
+		assert_eq!(
+			harness.screen.cursor(),
+			(len % width, len / width),
+			"width {}",
+			width
+		);
+
+		// Move back to the start of the line across the wrapped rows
+		harness.keys(&[KeyEvent::new(KeyCode::Home, KeyModifiers::NONE)]);
+		assert_eq!(harness.screen.cursor(), (2, 0), "width {}", width);
+		assert_eq!(harness.screen.text(), expected, "width {}", width);
+	}
+}
+
+#[test]
+fn line_filling_the_row_exactly() {
+	let mut harness = Harness::new("> ", 10, 4);
+	harness.type_str("12345678");
+	assert_eq!(harness.screen.text(), ["> 12345678"]);
+	assert_eq!(harness.screen.cursor(), (0, 1));
+
+	harness.key(KeyCode::Backspace);
+	assert_eq!(harness.screen.text(), ["> 1234567"]);
+	assert_eq!(harness.screen.cursor(), (9, 0));
+
+	harness.type_str("89");
+	assert_eq!(harness.screen.text(), ["> 12345678", "9"]);
+	assert_eq!(harness.screen.cursor(), (1, 1));
+}
+
+#[test]
+fn deleting_unwraps_lines() {
+	let mut harness = Harness::new("> ", 8, 4);
+	harness.type_str("abcdefghij");
+	assert_eq!(harness.screen.text(), ["> abcdef", "ghij"]);
+	for _ in 0..5 {
+		harness.key(KeyCode::Backspace);
+	}
+	assert_eq!(harness.screen.text(), ["> abcde"]);
+	assert_eq!(harness.screen.cursor(), (7, 0));
+}
+
+#[test]
+fn accepting_clears_the_line() {
+	let mut harness = Harness::new("> ", 6, 4);
+	let result = harness.type_str("one two\n");
+	assert_eq!(result.unwrap().unwrap(), "one two");
+	assert_eq!(harness.screen.text(), [">"]);
+	assert_eq!(harness.screen.cursor(), (2, 0));
+}
+
+#[test]
+fn prints_lines_above_the_prompt() {
+	for width in [6, 10, 30] {
+		let mut harness = Harness::new("> ", width, 6);
+		harness.type_str("abc");
+		harness.print("out\n");
+		assert_eq!(harness.screen.text(), ["out", "> abc"], "width {}", width);
+		assert_eq!(harness.screen.cursor(), (5, 1), "width {}", width);
+
+		harness.print("two\nlines\n");
+		assert_eq!(
+			harness.screen.text(),
+			["out", "two", "lines", "> abc"],
+			"width {}",
+			width
+		);
+		assert_eq!(harness.screen.cursor(), (5, 3), "width {}", width);
+	}
+}
+
+#[test]
+fn prints_partial_lines() {
+	let mut harness = Harness::new("> ", 20, 6);
+	harness.type_str("ab");
+	harness.print("abc");
+	assert_eq!(harness.screen.text(), ["abc", "> ab"]);
+	assert_eq!(harness.screen.cursor(), (4, 1));
+
+	// Further writes continue the partial line
+	harness.print("def");
+	assert_eq!(harness.screen.text(), ["abcdef", "> ab"]);
+
+	harness.print("ghi\n");
+	assert_eq!(harness.screen.text(), ["abcdefghi", "> ab"]);
+	assert_eq!(harness.screen.cursor(), (4, 1));
+
+	harness.print("next\n");
+	assert_eq!(harness.screen.text(), ["abcdefghi", "next", "> ab"]);
+}
+
+#[test]
+fn prints_partial_lines_wider_than_the_terminal() {
+	let mut harness = Harness::new("> ", 8, 6);
+	harness.print("123456");
+	harness.print("7890");
+	assert_eq!(harness.screen.text(), ["12345678", "90", ">"]);
+	assert_eq!(harness.screen.cursor(), (2, 2));
+
+	// Continue the partial line after a write ending exactly at the last column
+	harness.print("123456\n");
+	assert_eq!(harness.screen.text(), ["12345678", "90123456", ">"]);
+	assert_eq!(harness.screen.cursor(), (2, 2));
+
+	harness.print("abcdefgh");
+	harness.print("ij\n");
+	assert_eq!(
+		harness.screen.text(),
+		["12345678", "90123456", "abcdefgh", "ij", ">"]
+	);
+}
+
+#[test]
+fn prints_partial_lines_after_newlines() {
+	let mut harness = Harness::new("> ", 20, 6);
+	harness.print("first\nsecond");
+	harness.print(" part\n");
+	assert_eq!(harness.screen.text(), ["first", "second part", ">"]);
+	assert_eq!(harness.screen.cursor(), (2, 2));
+}
+
+#[test]
+fn prints_long_lines_with_a_wrapped_line_being_edited() {
+	let mut harness = Harness::new("> ", 8, 8);
+	harness.type_str("abcdefghij");
+	harness.print("0123456789\n");
+	assert_eq!(
+		harness.screen.text(),
+		["01234567", "89", "> abcdef", "ghij"]
+	);
+	assert_eq!(harness.screen.cursor(), (4, 3));
+}
+
+#[test]
+fn scrolls_at_the_bottom_of_the_screen() {
+	let mut harness = Harness::new("> ", 10, 3);
+	harness.type_str("abc");
+	for i in 0..5 {
+		harness.print(&format!("line {}\n", i));
+	}
+	assert_eq!(harness.screen.lines(), ["line 3", "line 4", "> abc"]);
+	assert_eq!(harness.screen.scrollback, ["line 0", "line 1", "line 2"]);
+	assert_eq!(harness.screen.cursor(), (5, 2));
+}
+
+#[test]
+fn wide_characters() {
+	let mut harness = Harness::new("> ", 7, 4);
+	harness.type_str("日本語");
+	assert_eq!(harness.screen.text(), ["> 日本", "語"]);
+	assert_eq!(harness.screen.cursor(), (2, 1));
+
+	harness.key(KeyCode::Left);
+	assert_eq!(harness.screen.cursor(), (0, 1));
+	harness.key(KeyCode::Left);
+	assert_eq!(harness.screen.cursor(), (4, 0));
+}
+
+#[test]
+fn resizing_rerenders_the_line() {
+	let mut harness = Harness::new("> ", 20, 5);
+	harness.type_str("abcdefghij");
+	harness.resize(6, 5);
+	assert_eq!(harness.screen.text(), ["> abcd", "efghij"]);
+	assert_eq!(harness.screen.cursor(), (0, 2));
+}
+
+#[test]
+fn pasted_tabs_move_to_tab_stops() {
+	let mut harness = Harness::new("> ", 20, 5);
+	harness.backend.push_paste("a\tb\tc");
+	harness.run();
+	assert_eq!(harness.screen.text(), ["> a     b       c"]);
+	assert_eq!(harness.screen.cursor(), (17, 0));
+
+	harness.key(KeyCode::Left);
+	harness.key(KeyCode::Left);
+	assert_eq!(harness.screen.cursor(), (9, 0));
+	let line = harness.type_str("\n");
+	assert_eq!(line.unwrap().unwrap(), "a\tb\tc");
+}
+
+#[test]
+fn pasted_newlines_do_not_submit() {
+	let mut harness = Harness::new("> ", 30, 5);
+	harness.backend.push_paste("select 1;\rselect 2;\r\n");
+	assert!(harness.run().is_none());
+	// Without a validator allowing multi-line input, newlines become spaces
+	assert_eq!(harness.screen.text(), ["> select 1; select 2;"]);
+	let line = harness.type_str("\n");
+	assert_eq!(line.unwrap().unwrap(), "select 1; select 2;");
+}
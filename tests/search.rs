@@ -0,0 +1,105 @@
+mod support;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use rustyline_async::EditCommand;
+use support::Harness;
+
+fn ctrl(c: char) -> KeyEvent {
+	KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+}
+
+#[test]
+fn backspace_returns_to_previous_match() {
+	let mut harness = Harness::with_history(&["cargo build", "cat notes", "cargo test"]);
+	harness.type_str("draft");
+	harness.keys(&[ctrl('r')]);
+	harness.type_str("ca");
+	assert_eq!(
+		harness.screen.text(),
+		["(reverse-i-search)'ca': cargo test"]
+	);
+	harness.keys(&[ctrl('r')]);
+	assert_eq!(harness.screen.text(), ["(reverse-i-search)'ca': cat notes"]);
+	harness.type_str("r");
+	assert_eq!(
+		harness.screen.text(),
+		["(reverse-i-search)'car': cargo build"]
+	);
+
+	// Back to the match before typing "r", not to the newest one
+	harness.key(KeyCode::Backspace);
+	assert_eq!(harness.screen.text(), ["(reverse-i-search)'ca': cat notes"]);
+	assert_eq!(harness.screen.cursor(), (24, 0));
+	harness.key(KeyCode::Backspace);
+	assert_eq!(
+		harness.screen.text(),
+		["(reverse-i-search)'ca': cargo test"]
+	);
+
+	// An empty query shows the original line again
+	harness.key(KeyCode::Backspace);
+	harness.key(KeyCode::Backspace);
+	assert_eq!(harness.screen.text(), ["(reverse-i-search)'': draft"]);
+	assert_eq!(harness.screen.cursor(), (27, 0));
+	let line = harness.type_str("\n");
+	assert_eq!(line.unwrap().unwrap(), "draft");
+}
+
+#[test]
+fn backspace_after_failed_search_shows_last_match() {
+	let mut harness = Harness::with_history(&["cargo build"]);
+	harness.keys(&[ctrl('r')]);
+	harness.type_str("bux");
+	assert_eq!(
+		harness.screen.text(),
+		["(failed reverse-i-search)'bux': cargo build"]
+	);
+	harness.key(KeyCode::Backspace);
+	assert_eq!(
+		harness.screen.text(),
+		["(reverse-i-search)'bu': cargo build"]
+	);
+	assert_eq!(harness.screen.cursor(), (30, 0));
+}
+
+#[test]
+fn search_keys_follow_the_keymap() {
+	let mut harness = Harness::with_history(&["cargo build", "cargo test"]);
+	harness.readline.unbind(&[ctrl('r')]);
+	harness
+		.readline
+		.bind(&[ctrl('t')], EditCommand::ReverseSearchHistory);
+	harness
+		.readline
+		.bind(&[ctrl('x')], EditCommand::BackwardDeleteChar);
+	harness.keys(&[ctrl('t')]);
+	harness.type_str("cargo");
+	assert_eq!(
+		harness.screen.text(),
+		["(reverse-i-search)'cargo': cargo test"]
+	);
+	harness.keys(&[ctrl('t')]);
+	assert_eq!(
+		harness.screen.text(),
+		["(reverse-i-search)'cargo': cargo build"]
+	);
+	harness.keys(&[ctrl('x')]);
+	assert_eq!(
+		harness.screen.text(),
+		["(reverse-i-search)'cargo': cargo test"]
+	);
+
+	// Unbound keys accept the match
+	harness.keys(&[ctrl('r')]);
+	assert_eq!(harness.screen.text(), ["> cargo test"]);
+}
+
+#[test]
+fn abort_restores_the_line() {
+	let mut harness = Harness::with_history(&["cargo build"]);
+	harness.type_str("draft");
+	harness.keys(&[ctrl('r')]);
+	harness.type_str("bu");
+	harness.keys(&[ctrl('g')]);
+	assert_eq!(harness.screen.text(), ["> draft"]);
+}
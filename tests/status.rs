@@ -0,0 +1,107 @@
+mod support;
+
+use crossterm::event::KeyCode;
+use rustyline_async::StatusPosition;
+use support::Harness;
+
+#[test]
+fn status_below_the_line() {
+	let mut harness = Harness::new("> ", 20, 6);
+	harness
+		.readline
+		.set_status(Some("connected".to_owned()))
+		.unwrap();
+	harness.update_screen();
+	assert_eq!(harness.screen.text(), [">", "connected"]);
+	assert_eq!(harness.screen.cursor(), (2, 0));
+
+	harness.type_str("hello");
+	assert_eq!(harness.screen.text(), ["> hello", "connected"]);
+	assert_eq!(harness.screen.cursor(), (7, 0));
+
+	harness.print("output\n");
+	assert_eq!(harness.screen.text(), ["output", "> hello", "connected"]);
+	assert_eq!(harness.screen.cursor(), (7, 1));
+
+	harness.readline.set_status(None).unwrap();
+	harness.update_screen();
+	assert_eq!(harness.screen.text(), ["output", "> hello"]);
+}
+
+#[test]
+fn status_above_the_line() {
+	let mut harness = Harness::new("> ", 20, 6);
+	harness
+		.readline
+		.set_status_position(StatusPosition::Above)
+		.unwrap();
+	harness
+		.readline
+		.set_status(Some("job 1: running\njob 2: done".to_owned()))
+		.unwrap();
+	harness.type_str("abc");
+	assert_eq!(
+		harness.screen.text(),
+		["job 1: running", "job 2: done", "> abc"]
+	);
+	assert_eq!(harness.screen.cursor(), (5, 2));
+
+	harness.key(KeyCode::Left);
+	harness.print("partial");
+	harness.print(" line\n");
+	assert_eq!(
+		harness.screen.text(),
+		["partial line", "job 1: running", "job 2: done", "> abc"]
+	);
+	assert_eq!(harness.screen.cursor(), (4, 3));
+}
+
+#[test]
+fn status_stays_out_of_the_scrollback() {
+	let mut harness = Harness::new("> ", 20, 3);
+	harness
+		.readline
+		.set_status(Some("status".to_owned()))
+		.unwrap();
+	for i in 0..4 {
+		harness.print(&format!("line {}\n", i));
+	}
+	assert_eq!(harness.screen.lines(), ["line 3", ">", "status"]);
+	assert_eq!(harness.screen.scrollback, ["line 0", "line 1", "line 2"]);
+}
+
+#[test]
+fn status_with_a_wrapped_line() {
+	let mut harness = Harness::new("> ", 8, 6);
+	harness
+		.readline
+		.set_status(Some(
+			"\x1b[1ma status\x1b[0m wider than the terminal".to_owned(),
+		))
+		.unwrap();
+	harness.type_str("abcdefghijklmn");
+	assert_eq!(
+		harness.screen.text(),
+		["> abcdef", "ghijklmn", "", "a status"]
+	);
+	assert_eq!(harness.screen.cursor(), (0, 2));
+	harness.key(KeyCode::Home);
+	assert_eq!(harness.screen.cursor(), (2, 0));
+}
+
+#[test]
+fn status_handle_and_resize() {
+	let mut harness = Harness::new("> ", 20, 6);
+	let handle = harness.readline.status_handle();
+	handle.set_status(Some("12:00".to_owned())).unwrap();
+	harness.type_str("x");
+	assert_eq!(harness.screen.text(), ["> x", "12:00"]);
+	handle
+		.set_status(Some("12:01 progress 50%".to_owned()))
+		.unwrap();
+	harness.run();
+	assert_eq!(harness.screen.text(), ["> x", "12:01 progress 50%"]);
+	harness.resize(10, 6);
+	assert_eq!(harness.screen.text(), ["> x", "12:01 prog"]);
+	assert_eq!(harness.screen.cursor(), (3, 0));
+}
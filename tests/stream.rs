@@ -0,0 +1,46 @@
+use std::{
+	io,
+	pin::Pin,
+	sync::{Arc, Mutex},
+	task::{Context, Poll},
+};
+
+use futures::{io::AsyncWrite, stream};
+use rustyline_async::Readline;
+
+/// Writer whose output can still be read after it is dropped
+#[derive(Clone, Default)]
+struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+impl AsyncWrite for SharedOutput {
+	fn poll_write(
+		self: Pin<&mut Self>,
+		_cx: &mut Context<'_>,
+		buf: &[u8],
+	) -> Poll<io::Result<usize>> {
+		self.0.lock().unwrap().extend_from_slice(buf);
+		Poll::Ready(Ok(buf.len()))
+	}
+	fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		Poll::Ready(Ok(()))
+	}
+	fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		Poll::Ready(Ok(()))
+	}
+}
+
+#[test]
+fn disables_bracketed_paste_when_dropped() {
+	let output = SharedOutput::default();
+	let (readline, _writer) = Readline::with_stream(
+		"> ".to_owned(),
+		futures::io::empty(),
+		output.clone(),
+		(80, 24),
+		stream::pending(),
+	)
+	.unwrap();
+	drop(readline);
+	let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+	assert!(output.contains("\x1b[?2004h"));
+	assert!(output.ends_with("\x1b[?2004l"));
+}
@@ -0,0 +1,312 @@
+//! Test support: a small VT100 screen emulator and a harness driving a `Readline` through a `MemoryBackend`
+#![allow(dead_code)]
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use futures::{io::AsyncWriteExt, FutureExt};
+use rustyline_async::{MemoryBackend, Readline, ReadlineError, SharedWriter};
+use std::io::Write;
+use unicode_width::UnicodeWidthChar;
+
+#[derive(Clone, PartialEq, Eq)]
+enum Cell {
+	Blank,
+	Text(String),
+	// Right half of a wide character
+	WideTail,
+}
+
+enum State {
+	Ground,
+	Escape,
+	Csi(String),
+}
+
+/// Screen of a terminal with automatic margins, interpreting printable text, carriage returns, line feeds
+/// and the CSI sequences crossterm emits for moving the cursor and clearing.
+/// Styles and modes are ignored.
+pub struct Screen {
+	columns: usize,
+	rows: usize,
+	grid: Vec<Vec<Cell>>,
+	// Column and row of the cursor
+	cursor: (usize, usize),
+	// Set after writing to the last column, the next character then wraps to the next row
+	wrap_pending: bool,
+	state: State,
+	/// Rows scrolled off the top of the screen, oldest first
+	pub scrollback: Vec<String>,
+}
+impl Screen {
+	pub fn new(columns: u16, rows: u16) -> Self {
+		let (columns, rows) = (columns as usize, rows as usize);
+		Self {
+			columns,
+			rows,
+			grid: vec![vec![Cell::Blank; columns]; rows],
+			cursor: (0, 0),
+			wrap_pending: false,
+			state: State::Ground,
+			scrollback: Vec::new(),
+		}
+	}
+	/// Column and row of the cursor, starting at 0
+	pub fn cursor(&self) -> (u16, u16) {
+		(self.cursor.0 as u16, self.cursor.1 as u16)
+	}
+	/// Text of every row, without trailing whitespace
+	pub fn lines(&self) -> Vec<String> {
+		self.grid.iter().map(|row| row_text(row)).collect()
+	}
+	/// Text of the rows up to the last non-empty one
+	pub fn text(&self) -> Vec<String> {
+		let mut lines = self.lines();
+		while lines.last().is_some_and(String::is_empty) {
+			lines.pop();
+		}
+		lines
+	}
+	/// Change the size of the screen, truncating or padding rows without reflowing them
+	pub fn resize(&mut self, columns: u16, rows: u16) {
+		let (columns, rows) = (columns as usize, rows as usize);
+		for row in &mut self.grid {
+			row.resize(columns, Cell::Blank);
+		}
+		// Keep the cursor on the screen by dropping rows from the top
+		while self.cursor.1 >= rows {
+			self.scroll_up(1);
+			self.cursor.1 -= 1;
+		}
+		self.grid.resize(rows, vec![Cell::Blank; columns]);
+		self.columns = columns;
+		self.rows = rows;
+		self.cursor.0 = self.cursor.0.min(columns - 1);
+		self.wrap_pending = false;
+	}
+	pub fn feed(&mut self, output: &[u8]) {
+		for c in String::from_utf8_lossy(output).chars() {
+			match std::mem::replace(&mut self.state, State::Ground) {
+				State::Ground => match c {
+					'\x1b' => self.state = State::Escape,
+					'\r' => self.move_to(0, self.cursor.1),
+					'\n' => {
+						self.line_feed();
+						self.wrap_pending = false;
+					}
+					'\x08' => self.move_to(self.cursor.0.saturating_sub(1), self.cursor.1),
+					'\t' => self.move_to((self.cursor.0 / 8 + 1) * 8, self.cursor.1),
+					c if c.is_control() => {}
+					c => self.print(c),
+				},
+				State::Escape => {
+					if c == '[' {
+						self.state = State::Csi(String::new());
+					}
+				}
+				State::Csi(mut params) => {
+					if ('\x40'..='\x7e').contains(&c) {
+						self.csi(&params, c);
+					} else {
+						params.push(c);
+						self.state = State::Csi(params);
+					}
+				}
+			}
+		}
+	}
+	fn print(&mut self, c: char) {
+		let width = c.width().unwrap_or(0);
+		if width == 0 {
+			// Combine with the previous character
+			let column = match self.wrap_pending {
+				true => self.cursor.0,
+				false => self.cursor.0.saturating_sub(1),
+			};
+			if let Cell::Text(text) = &mut self.grid[self.cursor.1][column] {
+				text.push(c);
+			}
+			return;
+		}
+		if self.wrap_pending || self.cursor.0 + width > self.columns {
+			self.cursor.0 = 0;
+			self.line_feed();
+		}
+		let (column, row) = self.cursor;
+		self.grid[row][column] = Cell::Text(c.to_string());
+		if width == 2 && column + 1 < self.columns {
+			self.grid[row][column + 1] = Cell::WideTail;
+		}
+		self.wrap_pending = false;
+		if column + width >= self.columns {
+			self.cursor.0 = self.columns - 1;
+			self.wrap_pending = true;
+		} else {
+			self.cursor.0 = column + width;
+		}
+	}
+	fn line_feed(&mut self) {
+		if self.cursor.1 + 1 == self.rows {
+			self.scroll_up(1);
+		} else {
+			self.cursor.1 += 1;
+		}
+	}
+	fn scroll_up(&mut self, count: usize) {
+		for _ in 0..count.min(self.rows) {
+			let row = self.grid.remove(0);
+			self.scrollback.push(row_text(&row));
+			self.grid.push(vec![Cell::Blank; self.columns]);
+		}
+	}
+	fn move_to(&mut self, column: usize, row: usize) {
+		self.cursor = (column.min(self.columns - 1), row.min(self.rows - 1));
+		self.wrap_pending = false;
+	}
+	fn csi(&mut self, params: &str, end: char) {
+		// Private modes, like line wrapping and bracketed paste
+		if params.starts_with('?') {
+			return;
+		}
+		let params = params
+			.split(';')
+			.map(|param| param.parse::<usize>().unwrap_or(0))
+			.collect::<Vec<_>>();
+		let param = |i: usize| params.get(i).copied().unwrap_or(0);
+		// Movements by 0 move by 1, as with real terminals
+		let count = param(0).max(1);
+		let (column, row) = self.cursor;
+		match end {
+			'A' => self.move_to(column, row.saturating_sub(count)),
+			'B' => self.move_to(column, row + count),
+			'C' => self.move_to(column + count, row),
+			'D' => self.move_to(column.saturating_sub(count), row),
+			'E' => self.move_to(0, row + count),
+			'F' => self.move_to(0, row.saturating_sub(count)),
+			'G' => self.move_to(count - 1, row),
+			'H' | 'f' => self.move_to(param(1).max(1) - 1, count - 1),
+			'J' => {
+				match param(0) {
+					0 => {
+						self.clear_row(row, column..self.columns);
+						for row in row + 1..self.rows {
+							self.clear_row(row, 0..self.columns);
+						}
+					}
+					1 => {
+						for row in 0..row {
+							self.clear_row(row, 0..self.columns);
+						}
+						self.clear_row(row, 0..column + 1);
+					}
+					_ => {
+						for row in 0..self.rows {
+							self.clear_row(row, 0..self.columns);
+						}
+					}
+				}
+				self.wrap_pending = false;
+			}
+			'K' => {
+				match param(0) {
+					0 => self.clear_row(row, column..self.columns),
+					1 => self.clear_row(row, 0..column + 1),
+					_ => self.clear_row(row, 0..self.columns),
+				}
+				self.wrap_pending = false;
+			}
+			'S' => self.scroll_up(count),
+			_ => {}
+		}
+	}
+	fn clear_row(&mut self, row: usize, columns: std::ops::Range<usize>) {
+		for cell in &mut self.grid[row][columns] {
+			*cell = Cell::Blank;
+		}
+	}
+}
+
+fn row_text(row: &[Cell]) -> String {
+	let text = row
+		.iter()
+		.map(|cell| match cell {
+			Cell::Blank => " ",
+			Cell::Text(text) => text,
+			Cell::WideTail => "",
+		})
+		.collect::<String>();
+	text.trim_end().to_owned()
+}
+
+/// `Readline` rendering to an emulated screen
+pub struct Harness {
+	pub readline: Readline,
+	pub writer: SharedWriter,
+	pub backend: MemoryBackend,
+	pub screen: Screen,
+}
+impl Harness {
+	pub fn new(prompt: &str, columns: u16, rows: u16) -> Self {
+		let backend = MemoryBackend::new((columns, rows));
+		let (readline, writer) =
+			Readline::with_backend(prompt.to_owned(), backend.clone()).unwrap();
+		let mut harness = Self {
+			readline,
+			writer,
+			backend,
+			screen: Screen::new(columns, rows),
+		};
+		harness.update_screen();
+		harness
+	}
+	/// Harness with the prompt `> ` on a 50 by 5 screen, with the entries added to the history, oldest first
+	pub fn with_history(entries: &[&str]) -> Self {
+		let mut harness = Self::new("> ", 50, 5);
+		for entry in entries {
+			harness
+				.readline
+				.add_history_entry(entry.to_string())
+				.unwrap();
+		}
+		harness
+	}
+	/// Let `readline()` process everything sent so far, returning its result if it completed
+	pub fn run(&mut self) -> Option<Result<String, ReadlineError>> {
+		let result = self.readline.readline().now_or_never();
+		self.update_screen();
+		result
+	}
+	pub fn update_screen(&mut self) {
+		self.screen.feed(&self.backend.take_output());
+	}
+	/// Press keys one after another
+	pub fn keys(&mut self, keys: &[KeyEvent]) -> Option<Result<String, ReadlineError>> {
+		for key in keys {
+			self.backend.push_key(key.code, key.modifiers);
+		}
+		self.run()
+	}
+	pub fn key(&mut self, code: KeyCode) -> Option<Result<String, ReadlineError>> {
+		self.keys(&[KeyEvent::new(code, KeyModifiers::NONE)])
+	}
+	/// Type text key by key, with newlines pressing Enter
+	pub fn type_str(&mut self, text: &str) -> Option<Result<String, ReadlineError>> {
+		self.backend.push_str(text);
+		self.run()
+	}
+	/// Write text through the `SharedWriter`, flushing it even if it doesn't end in a newline
+	pub fn print(&mut self, text: &str) {
+		Write::write_all(&mut self.writer, text.as_bytes()).unwrap();
+		if !text.ends_with('\n') {
+			AsyncWriteExt::flush(&mut self.writer)
+				.now_or_never()
+				.unwrap()
+				.unwrap();
+		}
+		self.run();
+	}
+	/// Resize both the screen and the terminal `Readline` sees
+	pub fn resize(&mut self, columns: u16, rows: u16) {
+		self.screen.resize(columns, rows);
+		self.backend.resize(columns, rows);
+		self.run();
+	}
+}
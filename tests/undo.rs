@@ -0,0 +1,67 @@
+#![cfg(feature = "emacs")]
+
+mod support;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use support::Harness;
+
+fn undo() -> KeyEvent {
+	KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL)
+}
+
+fn redo() -> KeyEvent {
+	KeyEvent::new(KeyCode::Char('_'), KeyModifiers::ALT)
+}
+
+#[test]
+fn typed_characters_are_undone_together() {
+	let mut harness = Harness::new("> ", 30, 5);
+	harness.type_str("hello world");
+	harness.keys(&[undo()]);
+	assert_eq!(harness.screen.text(), [">"]);
+	assert_eq!(harness.screen.cursor(), (2, 0));
+	harness.keys(&[redo()]);
+	assert_eq!(harness.screen.text(), ["> hello world"]);
+	assert_eq!(harness.screen.cursor(), (13, 0));
+}
+
+#[test]
+fn moving_the_cursor_starts_a_new_step() {
+	let mut harness = Harness::new("> ", 30, 5);
+	harness.type_str("abc");
+	harness.key(KeyCode::Left);
+	harness.type_str("XY");
+	harness.key(KeyCode::Backspace);
+	harness.key(KeyCode::Backspace);
+	assert_eq!(harness.screen.text(), ["> abc"]);
+
+	// Every deletion is a step of its own
+	harness.keys(&[undo()]);
+	assert_eq!(harness.screen.text(), ["> abXc"]);
+	harness.keys(&[undo()]);
+	assert_eq!(harness.screen.text(), ["> abXYc"]);
+	harness.keys(&[undo()]);
+	assert_eq!(harness.screen.text(), ["> abc"]);
+	assert_eq!(harness.screen.cursor(), (4, 0));
+	harness.keys(&[undo()]);
+	assert_eq!(harness.screen.text(), [">"]);
+	// Nothing left to undo
+	harness.keys(&[undo()]);
+	assert_eq!(harness.screen.text(), [">"]);
+
+	harness.keys(&[redo(), redo()]);
+	assert_eq!(harness.screen.text(), ["> abXYc"]);
+	assert_eq!(harness.screen.cursor(), (6, 0));
+}
+
+#[test]
+fn editing_after_undo_drops_the_redo_steps() {
+	let mut harness = Harness::new("> ", 30, 5);
+	harness.type_str("abc");
+	harness.keys(&[undo()]);
+	harness.type_str("x");
+	harness.keys(&[redo()]);
+	assert_eq!(harness.screen.text(), ["> x"]);
+	harness.keys(&[undo()]);
+	assert_eq!(harness.screen.text(), [">"]);
+}
@@ -0,0 +1,121 @@
+#![cfg(feature = "vi")]
+mod support;
+
+use crossterm::event::KeyCode;
+use rustyline_async::EditMode;
+use support::Harness;
+
+fn vi_harness(line: &str) -> Harness {
+	let mut harness = Harness::new("> ", 40, 5);
+	harness.readline.set_edit_mode(EditMode::Vi).unwrap();
+	harness.type_str(line);
+	harness.key(KeyCode::Esc);
+	harness
+}
+
+#[test]
+fn pasting_in_normal_mode_runs_commands() {
+	let mut harness = vi_harness("one two three");
+	harness.type_str("0");
+	harness.backend.push_paste("dw");
+	harness.run();
+	assert_eq!(harness.screen.text(), ["> two three"]);
+	assert_eq!(harness.screen.cursor(), (2, 0));
+}
+
+#[test]
+fn deletes_words() {
+	let mut harness = vi_harness("one two three four");
+	harness.type_str("0dw");
+	assert_eq!(harness.screen.text(), ["> two three four"]);
+	assert_eq!(harness.screen.cursor(), (2, 0));
+	harness.type_str("2dw");
+	assert_eq!(harness.screen.text(), ["> four"]);
+	assert_eq!(harness.screen.cursor(), (2, 0));
+
+	harness.type_str("u");
+	assert_eq!(harness.screen.text(), ["> two three four"]);
+	harness.type_str("u");
+	assert_eq!(harness.screen.text(), ["> one two three four"]);
+}
+
+#[test]
+fn changes_word_and_repeats() {
+	let mut harness = vi_harness("one two three");
+	harness.type_str("0cwONE");
+	harness.key(KeyCode::Esc);
+	assert_eq!(harness.screen.text(), ["> ONE two three"]);
+	assert_eq!(harness.screen.cursor(), (4, 0));
+	harness.type_str("w.");
+	assert_eq!(harness.screen.text(), ["> ONE ONE three"]);
+	assert_eq!(harness.screen.cursor(), (8, 0));
+}
+
+#[test]
+fn deletes_until_character() {
+	let mut harness = vi_harness("call(a, b) + 1");
+	harness.type_str("0f(ldt)");
+	assert_eq!(harness.screen.text(), ["> call() + 1"]);
+	assert_eq!(harness.screen.cursor(), (7, 0));
+}
+
+#[test]
+fn swaps_characters() {
+	let mut harness = vi_harness("abc");
+	harness.type_str("0xp");
+	assert_eq!(harness.screen.text(), ["> bac"]);
+	assert_eq!(harness.screen.cursor(), (3, 0));
+}
+
+#[test]
+fn repeats_insert() {
+	let mut harness = vi_harness("ab");
+	harness.type_str("0ix");
+	harness.key(KeyCode::Esc);
+	assert_eq!(harness.screen.text(), ["> xab"]);
+	assert_eq!(harness.screen.cursor(), (2, 0));
+	harness.type_str("..");
+	assert_eq!(harness.screen.text(), ["> xxxab"]);
+	let line = harness.type_str("\n");
+	assert_eq!(line.unwrap().unwrap(), "xxxab");
+}
+
+#[test]
+fn yanking_lines_keeps_cursor() {
+	let mut harness = vi_harness("one two");
+	harness.type_str("0wyy");
+	assert_eq!(harness.screen.cursor(), (6, 0));
+	harness.type_str("$P");
+	assert_eq!(harness.screen.text(), ["> one twone twoo"]);
+
+	// Yanking backwards moves to the start of the yanked text
+	harness.type_str("yb");
+	assert_eq!(harness.screen.cursor(), (12, 0));
+}
+
+#[test]
+fn changes_word_from_its_last_character() {
+	let mut harness = vi_harness("ab cd");
+	harness.type_str("0lcwX");
+	harness.key(KeyCode::Esc);
+	assert_eq!(harness.screen.text(), ["> aX cd"]);
+}
+
+#[test]
+fn deletes_nothing_until_the_next_character() {
+	let mut harness = vi_harness("abc");
+	harness.type_str("0dtb");
+	assert_eq!(harness.screen.text(), ["> abc"]);
+	assert_eq!(harness.screen.cursor(), (2, 0));
+}
+
+#[test]
+fn large_counts_are_capped() {
+	let mut harness = vi_harness("abc");
+	harness.type_str("9999999999h");
+	assert_eq!(harness.screen.cursor(), (2, 0));
+	harness.type_str("9999999999.");
+	harness.type_str("yl99999999999999999999p");
+	let line = harness.type_str("\n").unwrap().unwrap();
+	assert_eq!(line.len(), 3 + 9999);
+}
@@ -0,0 +1,159 @@
+mod support;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use futures::FutureExt;
+use rustyline_async::ReadlineError;
+use support::Harness;
+
+fn options() -> Vec<String> {
+	["red", "green", "blue", "yellow", "purple"]
+		.iter()
+		.map(|option| option.to_string())
+		.collect()
+}
+
+#[test]
+fn confirm_replaces_and_restores_line() {
+	let mut harness = Harness::new("> ", 30, 5);
+	harness.type_str("rm -rf");
+	let mut confirm = Box::pin(
+		harness
+			.readline
+			.confirm("Really delete? ".to_owned(), Some(false)),
+	);
+	assert!(confirm.as_mut().now_or_never().is_none());
+	harness.screen.feed(&harness.backend.take_output());
+	assert_eq!(harness.screen.text(), ["Really delete? [y/N]"]);
+	assert_eq!(harness.screen.cursor(), (21, 0));
+
+	// Other keys are ignored
+	harness.backend.push_str("xy");
+	assert!(confirm.now_or_never().unwrap().unwrap());
+	harness.update_screen();
+	assert_eq!(harness.screen.text(), ["> rm -rf"]);
+	assert_eq!(harness.screen.cursor(), (8, 0));
+}
+
+#[test]
+fn confirm_takes_default_on_enter() {
+	let mut harness = Harness::new("> ", 30, 5);
+	harness.backend.push_str("\n");
+	let answer = harness
+		.readline
+		.confirm("Continue? ".to_owned(), Some(true))
+		.now_or_never();
+	assert!(answer.unwrap().unwrap());
+
+	harness.backend.push_str("\nn");
+	let answer = harness
+		.readline
+		.confirm("Continue? ".to_owned(), None)
+		.now_or_never();
+	assert!(!answer.unwrap().unwrap());
+}
+
+#[test]
+fn select_moves_with_arrow_keys_and_scrolls() {
+	let mut harness = Harness::new("> ", 20, 4);
+	harness.type_str("draft");
+	let mut select = Box::pin(harness.readline.select("Colour:".to_owned(), options()));
+	assert!(select.as_mut().now_or_never().is_none());
+	harness.screen.feed(&harness.backend.take_output());
+	assert_eq!(
+		harness.screen.text(),
+		["Colour:", "> red", "  green", "  blue"]
+	);
+	assert_eq!(harness.screen.cursor(), (7, 0));
+
+	for _ in 0..3 {
+		harness.backend.push_key(KeyCode::Down, KeyModifiers::NONE);
+	}
+	assert!(select.as_mut().now_or_never().is_none());
+	harness.screen.feed(&harness.backend.take_output());
+	assert_eq!(
+		harness.screen.text(),
+		["Colour:", "  green", "  blue", "> yellow"]
+	);
+
+	// Moving up past the first option wraps around to the last
+	for _ in 0..4 {
+		harness.backend.push_key(KeyCode::Up, KeyModifiers::NONE);
+	}
+	assert!(select.as_mut().now_or_never().is_none());
+	harness.screen.feed(&harness.backend.take_output());
+	assert_eq!(
+		harness.screen.text(),
+		["Colour:", "  blue", "  yellow", "> purple"]
+	);
+
+	harness.backend.push_str("k\n");
+	assert_eq!(select.now_or_never().unwrap().unwrap(), 3);
+	harness.update_screen();
+	assert_eq!(harness.screen.text(), ["> draft"]);
+}
+
+#[test]
+fn widgets_are_interrupted_by_ctrl_c() {
+	let mut harness = Harness::new("> ", 20, 5);
+	harness.type_str("abc");
+	harness
+		.backend
+		.push_key(KeyCode::Char('c'), KeyModifiers::CONTROL);
+	let answer = harness
+		.readline
+		.select("Colour:".to_owned(), options())
+		.now_or_never();
+	assert!(matches!(answer, Some(Err(ReadlineError::Interrupted))));
+
+	harness.backend.push_key(KeyCode::Esc, KeyModifiers::NONE);
+	let answer = harness
+		.readline
+		.confirm("Sure? ".to_owned(), None)
+		.now_or_never();
+	assert!(matches!(answer, Some(Err(ReadlineError::Interrupted))));
+	harness.update_screen();
+	assert_eq!(harness.screen.text(), ["> abc"]);
+}
+
+#[test]
+fn read_key_returns_any_key_and_prints_output_above() {
+	let mut harness = Harness::new("> ", 30, 5);
+	let mut key = Box::pin(
+		harness
+			.readline
+			.read_key("Press any key to continue".to_owned()),
+	);
+	std::io::Write::write_all(&mut harness.writer, b"working\n").unwrap();
+	assert!(key.as_mut().now_or_never().is_none());
+	harness.screen.feed(&harness.backend.take_output());
+	assert_eq!(
+		harness.screen.text(),
+		["working", "Press any key to continue"]
+	);
+
+	harness.backend.push_key(KeyCode::Esc, KeyModifiers::NONE);
+	assert_eq!(
+		key.now_or_never().unwrap().unwrap(),
+		KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)
+	);
+	harness.update_screen();
+	assert_eq!(harness.screen.text(), ["working", ">"]);
+}
+
+#[test]
+fn pasting_into_widgets_presses_keys() {
+	let mut harness = Harness::new("> ", 30, 5);
+	harness.backend.push_paste("xn");
+	let answer = harness
+		.readline
+		.confirm("Continue? ".to_owned(), None)
+		.now_or_never();
+	assert!(!answer.unwrap().unwrap());
+
+	harness.backend.push_paste("jj\n");
+	let answer = harness
+		.readline
+		.select("Colour:".to_owned(), options())
+		.now_or_never();
+	assert_eq!(answer.unwrap().unwrap(), 2);
+}